@@ -4,9 +4,12 @@ use axum::{
     middleware::Next,
     response::{IntoResponse, Response},
 };
+use chrono::Utc;
 use serde::Deserialize;
+use subtle::ConstantTimeEq;
 
 use crate::state::AppState;
+use crate::token_store::TokenGrant;
 
 #[derive(Deserialize)]
 pub struct AuthQuery {
@@ -15,38 +18,61 @@ pub struct AuthQuery {
 }
 
 /// Middleware to validate authentication token from query parameter
+/// On success the resolved `TokenGrant` is added to the request extensions
 pub async fn validate_token(
     State(state): State<AppState>,
     Query(query): Query<AuthQuery>,
-    request: axum::extract::Request,
+    mut request: axum::extract::Request,
     next: Next,
 ) -> Result<Response, impl IntoResponse> {
-    // Skip authentication for obfuscated download routes
-    // The obfuscated token itself serves as authentication
+    // Skip authentication for signed download routes
+    // The URL signature itself serves as authentication
     if request.uri().path().starts_with("/download/") {
         return Ok(next.run(request).await);
     }
 
-    // If no auth token is configured, allow all requests
-    let Some(expected_token) = &state.auth_token else {
+    // If no auth token or token store is configured, allow all requests
+    if state.auth_token.is_none() && state.token_store.is_none() {
         return Ok(next.run(request).await);
+    }
+
+    // If authentication is configured, a token must be provided
+    let Some(provided_token) = query.token else {
+        tracing::warn!("Authentication token required but not provided");
+        return Err((StatusCode::UNAUTHORIZED, "Authentication token required"));
     };
 
-    // If auth token is configured, validate the provided token
-    match query.token {
-        Some(provided_token) if provided_token == *expected_token => {
-            // Token is valid, proceed
-            Ok(next.run(request).await)
-        }
-        Some(_) => {
-            // Token provided but incorrect
-            tracing::warn!("Invalid authentication token provided");
-            Err((StatusCode::UNAUTHORIZED, "Invalid authentication token"))
-        }
-        None => {
-            // No token provided but required
-            tracing::warn!("Authentication token required but not provided");
-            Err((StatusCode::UNAUTHORIZED, "Authentication token required"))
+    // The legacy AUTH_TOKEN grants access to every app
+    let is_legacy_token = state
+        .auth_token
+        .as_ref()
+        .is_some_and(|expected| bool::from(expected.as_bytes().ct_eq(provided_token.as_bytes())));
+
+    let grant = if is_legacy_token {
+        TokenGrant::unrestricted()
+    } else if let Some(ref token_store) = state.token_store {
+        match token_store
+            .snapshot()
+            .await
+            .resolve(&provided_token, Utc::now())
+        {
+            Ok(grant) => grant,
+            Err(rejection) => {
+                tracing::warn!("Invalid authentication token provided: {}", rejection);
+                return Err((StatusCode::UNAUTHORIZED, "Invalid authentication token"));
+            }
         }
+    } else {
+        // Token provided but incorrect
+        tracing::warn!("Invalid authentication token provided");
+        return Err((StatusCode::UNAUTHORIZED, "Invalid authentication token"));
+    };
+
+    if let Some(ref holder) = grant.holder {
+        tracing::debug!("Authenticated request for token holder: {}", holder);
     }
+
+    // Token is valid, proceed
+    request.extensions_mut().insert(grant);
+    Ok(next.run(request).await)
 }
@@ -1,4 +1,8 @@
-use std::collections::HashMap;
+use crate::models::InjectedLibrary;
+use crate::provision::SigningInfo;
+use axum::body::Bytes;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -14,6 +18,23 @@ pub struct CachedIpaInfo {
     pub bundle_version: String,
     pub bundle_short_version: Option<String>,
     pub bundle_name: String,
+    pub min_os_version: Option<String>,
+    /// Hex-encoded SHA-256 of the whole IPA file
+    pub sha256: Option<String>,
+    /// Archive path of the primary app icon
+    pub icon_path: Option<String>,
+    /// Entitlement names read from the code signature
+    pub entitlements: Option<Vec<String>>,
+    /// NS*UsageDescription keys from Info.plist
+    pub privacy: HashMap<String, String>,
+    /// True if the main executable is FairPlay-encrypted
+    pub encrypted: bool,
+    /// Signing details from embedded.mobileprovision
+    pub signing: Option<SigningInfo>,
+    /// Tweak dylibs and frameworks loaded by the main executable
+    pub injected_libraries: Vec<InjectedLibrary>,
+    /// Newest modification time of the archive's entries
+    pub archive_date: Option<DateTime<Utc>>,
 }
 
 /// Thread-safe cache for IPA metadata
@@ -70,6 +91,24 @@ impl IpaCache {
         let mut cache = self.inner.write().await;
         cache.insert(key, value);
     }
+
+    /// Records the SHA-256 of a cached IPA once it has been computed
+    pub async fn set_sha256(&self, key: &CacheKey, sha256: String) {
+        if let Some(info) = self.inner.write().await.get_mut(key) {
+            info.sha256 = Some(sha256);
+        }
+    }
+
+    /// Drops the entries of files that are no longer indexed, e.g. replaced or deleted IPAs
+    pub async fn retain(&self, live: &HashSet<CacheKey>) {
+        let mut cache = self.inner.write().await;
+        let before = cache.len();
+        cache.retain(|key, _| live.contains(key));
+
+        if cache.len() < before {
+            tracing::debug!("Evicted {} stale IPA cache entries", before - cache.len());
+        }
+    }
 }
 
 impl Default for IpaCache {
@@ -78,10 +117,73 @@ impl Default for IpaCache {
     }
 }
 
+/// Normalized PNG icons, one per app, tagged with the IPA they were extracted from
+/// An entry is only served while the app's newest IPA is still that file, unmodified
+#[derive(Clone, Default)]
+pub struct IconCache {
+    inner: Arc<RwLock<HashMap<String, (CacheKey, Bytes)>>>,
+}
+
+impl IconCache {
+    /// Retrieves an app's icon if it was extracted from the given IPA
+    pub async fn get(&self, app_name: &str, key: &CacheKey) -> Option<Bytes> {
+        let cache = self.inner.read().await;
+        cache
+            .get(app_name)
+            .filter(|(cached_key, _)| cached_key == key)
+            .map(|(_, png)| png.clone())
+    }
+
+    /// Stores an app's icon, replacing the one extracted from an older IPA
+    pub async fn insert(&self, app_name: String, key: CacheKey, png: Bytes) {
+        let mut cache = self.inner.write().await;
+        cache.insert(app_name, (key, png));
+    }
+
+    /// Drops the icons of IPAs that are no longer indexed
+    pub async fn retain(&self, live: &HashSet<CacheKey>) {
+        let mut cache = self.inner.write().await;
+        cache.retain(|_, (key, _)| live.contains(key));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_retain_evicts_stale_entries() {
+        let cache = IpaCache::new();
+        let info = CachedIpaInfo {
+            bundle_identifier: "com.example.app".to_string(),
+            bundle_version: "1".to_string(),
+            bundle_short_version: None,
+            bundle_name: "App".to_string(),
+            min_os_version: None,
+            sha256: None,
+            icon_path: None,
+            entitlements: None,
+            privacy: HashMap::new(),
+            encrypted: false,
+            signing: None,
+            injected_libraries: Vec::new(),
+            archive_date: None,
+        };
+        let old = (PathBuf::from("/apps/App/app.ipa"), 1);
+        let new = (PathBuf::from("/apps/App/app.ipa"), 2);
+        cache.insert(old.clone(), info.clone()).await;
+        cache.insert(new.clone(), info).await;
+
+        cache.set_sha256(&new, "abc".to_string()).await;
+        cache.retain(&HashSet::from([new.clone()])).await;
+
+        assert!(cache.get(&old).await.is_none());
+        assert_eq!(
+            cache.get(&new).await.unwrap().sha256.as_deref(),
+            Some("abc")
+        );
+    }
+
     #[tokio::test]
     async fn test_cache_miss() {
         let cache = IpaCache::new();
@@ -98,6 +200,15 @@ mod tests {
             bundle_version: "1.0.0".to_string(),
             bundle_short_version: Some("1.0".to_string()),
             bundle_name: "TestApp".to_string(),
+            min_os_version: None,
+            sha256: None,
+            icon_path: None,
+            entitlements: None,
+            privacy: HashMap::new(),
+            encrypted: false,
+            signing: None,
+            injected_libraries: Vec::new(),
+            archive_date: None,
         };
 
         cache.insert(key.clone(), info.clone()).await;
@@ -120,6 +231,15 @@ mod tests {
             bundle_version: "1.0.0".to_string(),
             bundle_short_version: None,
             bundle_name: "TestApp".to_string(),
+            min_os_version: None,
+            sha256: None,
+            icon_path: None,
+            entitlements: None,
+            privacy: HashMap::new(),
+            encrypted: false,
+            signing: None,
+            injected_libraries: Vec::new(),
+            archive_date: None,
         };
 
         cache.insert(key1, info).await;
@@ -140,6 +260,15 @@ mod tests {
             bundle_version: "100".to_string(),
             bundle_short_version: Some("1.0.0".to_string()),
             bundle_name: "Test App".to_string(),
+            min_os_version: None,
+            sha256: None,
+            icon_path: None,
+            entitlements: None,
+            privacy: HashMap::new(),
+            encrypted: false,
+            signing: None,
+            injected_libraries: Vec::new(),
+            archive_date: None,
         };
 
         // Insert into cache
@@ -171,6 +300,15 @@ mod tests {
             bundle_version: "200".to_string(),
             bundle_short_version: Some("2.0.0".to_string()),
             bundle_name: "My App".to_string(),
+            min_os_version: None,
+            sha256: None,
+            icon_path: None,
+            entitlements: None,
+            privacy: HashMap::new(),
+            encrypted: false,
+            signing: None,
+            injected_libraries: Vec::new(),
+            archive_date: None,
         };
 
         // Insert with original mtime
@@ -206,6 +344,15 @@ mod tests {
             bundle_version: "100".to_string(),
             bundle_short_version: Some("1.0.0".to_string()),
             bundle_name: "Shared App v1".to_string(),
+            min_os_version: None,
+            sha256: None,
+            icon_path: None,
+            entitlements: None,
+            privacy: HashMap::new(),
+            encrypted: false,
+            signing: None,
+            injected_libraries: Vec::new(),
+            archive_date: None,
         };
 
         let info_v2 = CachedIpaInfo {
@@ -213,6 +360,15 @@ mod tests {
             bundle_version: "110".to_string(),
             bundle_short_version: Some("1.1.0".to_string()),
             bundle_name: "Shared App v2".to_string(),
+            min_os_version: None,
+            sha256: None,
+            icon_path: None,
+            entitlements: None,
+            privacy: HashMap::new(),
+            encrypted: false,
+            signing: None,
+            injected_libraries: Vec::new(),
+            archive_date: None,
         };
 
         // Insert both versions
@@ -236,4 +392,53 @@ mod tests {
         assert_eq!(cached_v2.bundle_version, "110");
         assert_eq!(cached_v2.bundle_name, "Shared App v2");
     }
+
+    #[tokio::test]
+    async fn test_icon_cache_is_keyed_by_ipa() {
+        let cache = IconCache::default();
+        let old = (PathBuf::from("/apps/App/app.ipa"), 1);
+        let new = (PathBuf::from("/apps/App/app.ipa"), 2);
+
+        cache
+            .insert("App".to_string(), old.clone(), Bytes::from_static(b"old"))
+            .await;
+        assert_eq!(cache.get("App", &old).await.as_deref(), Some(&b"old"[..]));
+        // A modified IPA misses until its icon is extracted again
+        assert!(cache.get("App", &new).await.is_none());
+
+        cache
+            .insert("App".to_string(), new.clone(), Bytes::from_static(b"new"))
+            .await;
+        assert!(cache.get("App", &old).await.is_none());
+        assert_eq!(cache.get("App", &new).await.as_deref(), Some(&b"new"[..]));
+    }
+
+    #[tokio::test]
+    async fn test_icon_cache_retain_evicts_removed_ipas() {
+        let cache = IconCache::default();
+        let kept = (PathBuf::from("/apps/Kept/app.ipa"), 1);
+        let removed = (PathBuf::from("/apps/Removed/app.ipa"), 1);
+
+        cache
+            .insert(
+                "Kept".to_string(),
+                kept.clone(),
+                Bytes::from_static(b"kept"),
+            )
+            .await;
+        cache
+            .insert(
+                "Removed".to_string(),
+                removed.clone(),
+                Bytes::from_static(b"gone"),
+            )
+            .await;
+        cache.retain(&HashSet::from([kept.clone()])).await;
+
+        assert_eq!(
+            cache.get("Kept", &kept).await.as_deref(),
+            Some(&b"kept"[..])
+        );
+        assert!(cache.inner.read().await.get("Removed").is_none());
+    }
 }
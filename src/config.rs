@@ -0,0 +1,375 @@
+use crate::discovery::{is_valid_path_component, AppFiles, APP_FILE};
+use crate::models::{AppConfig, Config};
+use crate::reload::{spawn_file_reloader, Shared};
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Thread-safe handle to the current configuration
+pub type ConfigHandle = Shared<Config>;
+
+/// Reads, parses and validates config.json
+pub fn load_config(path: &Path) -> Result<Config> {
+    let content = std::fs::read_to_string(path).context("Failed to read config.json")?;
+    let config: Config = serde_json::from_str(&content).context("Failed to parse config.json")?;
+    validate_config(&config)?;
+    Ok(config)
+}
+
+/// Checks invariants that serde can't express
+fn validate_config(config: &Config) -> Result<()> {
+    let mut names = HashSet::new();
+    let mut directories = HashSet::new();
+
+    for app in &config.apps {
+        validate_app(app)?;
+        if !names.insert(app.name.as_str()) {
+            anyhow::bail!("App name {} is used more than once", app.name);
+        }
+        if let Some(ref directory) = app.directory {
+            if !directories.insert(directory.as_str()) {
+                anyhow::bail!("Directory {} is used by more than one app", directory);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks the invariants of a single app entry
+fn validate_app(app: &AppConfig) -> Result<()> {
+    if app.name.trim().is_empty() {
+        anyhow::bail!(
+            "App with bundle identifier {} has an empty name",
+            app.bundle_identifier
+        );
+    }
+    if app.bundle_identifier.trim().is_empty() {
+        anyhow::bail!("App {} has an empty bundleIdentifier", app.name);
+    }
+    if let Some(ref directory) = app.directory {
+        if !is_valid_path_component(directory) {
+            anyhow::bail!("App {} has an invalid directory: {}", app.name, directory);
+        }
+    }
+
+    Ok(())
+}
+
+/// An app.json that couldn't be applied to the configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppFileError {
+    /// App directory holding the file
+    pub directory: String,
+    pub message: String,
+}
+
+/// Applies the app.json files of the app directories on top of config.json
+/// An app.json extends the config.json entry of the app stored in its directory (by
+/// `directory`, name or bundle identifier), its top-level keys replacing the entry's;
+/// without such an entry it must describe a complete app. Either way the app is pinned
+/// to the directory. Files that don't make a valid app are skipped and returned, so they
+/// only take their own app down.
+pub fn apply_app_files(config: &Config, app_files: &AppFiles) -> (Config, Vec<AppFileError>) {
+    let mut merged = config.clone();
+    let mut errors = Vec::new();
+
+    // Apply in a stable order so name clashes always resolve the same way
+    let mut directories: Vec<&String> = app_files.keys().collect();
+    directories.sort();
+
+    for directory in directories {
+        let result = app_files[directory]
+            .as_ref()
+            .map_err(Clone::clone)
+            .and_then(|app_file| apply_app_file(&mut merged, directory, app_file));
+
+        if let Err(message) = result {
+            errors.push(AppFileError {
+                directory: directory.clone(),
+                message,
+            });
+        }
+    }
+
+    (merged, errors)
+}
+
+/// config.json with the app.json files applied
+pub struct MergedConfig {
+    pub config: Config,
+    /// app.json files that were skipped
+    pub app_file_errors: Vec<AppFileError>,
+}
+
+/// Snapshots a merged configuration was built from, and the result
+type MergedEntry = (Arc<Config>, Arc<AppFiles>, Arc<MergedConfig>);
+
+/// Merged configuration of the latest config.json and app.json snapshots
+/// Merging, and logging the files that fail, happens once per change rather than per request
+#[derive(Clone, Default)]
+pub struct MergedConfigCache {
+    inner: Arc<Mutex<Option<MergedEntry>>>,
+}
+
+impl MergedConfigCache {
+    /// Returns the merged configuration, merging again if either snapshot was replaced
+    pub async fn get(&self, config: Arc<Config>, app_files: Arc<AppFiles>) -> Arc<MergedConfig> {
+        let mut cached = self.inner.lock().await;
+        if let Some((ref cached_config, ref cached_files, ref merged)) = *cached {
+            if Arc::ptr_eq(cached_config, &config) && Arc::ptr_eq(cached_files, &app_files) {
+                return merged.clone();
+            }
+        }
+
+        let (merged, app_file_errors) = apply_app_files(&config, &app_files);
+        for err in &app_file_errors {
+            tracing::warn!("Skipping {}/{}: {}", err.directory, APP_FILE, err.message);
+        }
+
+        let merged = Arc::new(MergedConfig {
+            config: merged,
+            app_file_errors,
+        });
+        *cached = Some((config, app_files, merged.clone()));
+        merged
+    }
+}
+
+/// Merges one app.json into the configuration
+fn apply_app_file(
+    config: &mut Config,
+    directory: &str,
+    app_file: &serde_json::Map<String, serde_json::Value>,
+) -> std::result::Result<(), String> {
+    let bundle_identifier = app_file.get("bundleIdentifier").and_then(|id| id.as_str());
+    let position = config
+        .apps
+        .iter()
+        .position(|app| app.directory.as_deref().unwrap_or(&app.name) == directory)
+        .or_else(|| {
+            config.apps.iter().position(|app| {
+                app.directory.is_none() && Some(app.bundle_identifier.as_str()) == bundle_identifier
+            })
+        });
+    let central = position.map(|i| &config.apps[i]);
+
+    let mut entry = match central {
+        Some(app) => match serde_json::to_value(app) {
+            Ok(serde_json::Value::Object(entry)) => entry,
+            _ => return Err(format!("Failed to merge {} with config.json", APP_FILE)),
+        },
+        None => serde_json::Map::new(),
+    };
+    entry.extend(app_file.clone());
+
+    let mut app: AppConfig = serde_json::from_value(serde_json::Value::Object(entry))
+        .map_err(|err| format!("Invalid {}: {}", APP_FILE, err))?;
+
+    // Settings that are never published have to be carried over by hand
+    if let Some(central) = central {
+        if !app_file.contains_key("filenamePatterns") {
+            app.filename_patterns = central.filename_patterns.clone();
+        }
+        if !app_file.contains_key("versionOrder") {
+            app.version_order = central.version_order;
+        }
+    }
+    app.directory = Some(directory.to_string());
+
+    validate_app(&app).map_err(|err| format!("Invalid {}: {}", APP_FILE, err))?;
+    for (i, other) in config.apps.iter().enumerate() {
+        if Some(i) == position {
+            continue;
+        }
+        if other.name == app.name {
+            return Err(format!(
+                "App name {} is already used by another app",
+                app.name
+            ));
+        }
+        if other.directory.as_deref() == Some(directory) {
+            return Err(format!(
+                "Directory {} is pinned by app {}",
+                directory, other.name
+            ));
+        }
+    }
+
+    match position {
+        Some(i) => config.apps[i] = app,
+        None => config.apps.push(app),
+    }
+    Ok(())
+}
+
+/// Reloads config.json and swaps it in if it is valid
+/// On error the previous configuration is kept
+async fn reload(path: &Path, handle: &ConfigHandle) {
+    match load_config(path) {
+        Ok(config) => {
+            tracing::info!("Reloaded configuration for: {}", config.name);
+            handle.replace(config).await;
+        }
+        Err(err) => {
+            tracing::error!(
+                "Rejected new config.json, keeping the previous configuration: {:#}",
+                err
+            );
+        }
+    }
+}
+
+/// Starts reloading config.json when it changes on disk or when the process receives SIGHUP
+pub fn spawn_config_reloader(path: PathBuf, handle: ConfigHandle) -> Result<()> {
+    let config_path = path.clone();
+    spawn_file_reloader(path, move || {
+        let path = config_path.clone();
+        let handle = handle.clone();
+        async move { reload(&path, &handle).await }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minimal_config(apps: &str) -> String {
+        format!(
+            r#"{{
+                "name": "Repo",
+                "identifier": "com.example.repo",
+                "website": "https://example.com",
+                "tintColor": "ffffff",
+                "iconURL": "https://example.com/icon.png",
+                "sourceURL": "https://example.com",
+                "apps": [{}]
+            }}"#,
+            apps
+        )
+    }
+
+    fn app(name: &str) -> String {
+        format!(
+            r#"{{
+                "name": "{}",
+                "bundleIdentifier": "com.example.{}",
+                "developerName": "Dev",
+                "localizedDescription": "",
+                "iconURL": "",
+                "tintColor": "ffffff",
+                "category": "utilities",
+                "screenshotURLs": [],
+                "appPermissions": {{ "entitlements": [], "privacy": {{}} }}
+            }}"#,
+            name,
+            name.to_lowercase()
+        )
+    }
+
+    #[test]
+    fn test_validate_config_accepts_valid_config() {
+        let config: Config =
+            serde_json::from_str(&minimal_config(&format!("{},{}", app("A"), app("B")))).unwrap();
+        assert!(validate_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_validate_config_rejects_duplicate_names() {
+        let config: Config =
+            serde_json::from_str(&minimal_config(&format!("{},{}", app("A"), app("A")))).unwrap();
+        assert!(validate_config(&config).is_err());
+    }
+
+    fn app_file(json: &str) -> crate::discovery::AppFile {
+        Ok(serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn test_apply_app_files() {
+        let config: Config =
+            serde_json::from_str(&minimal_config(&format!("{},{}", app("A"), app("B")))).unwrap();
+        let app_files = AppFiles::from([
+            // Extends the config.json entry of A
+            (
+                "A".to_string(),
+                app_file(r#"{"subtitle": "From app.json"}"#),
+            ),
+            // A complete app dropped into its own folder
+            (
+                "C".to_string(),
+                Ok(serde_json::from_str(&app("C")).unwrap()),
+            ),
+            // Incomplete and not in config.json
+            ("D".to_string(), app_file(r#"{"name": "D"}"#)),
+            // Clashes with B
+            (
+                "E".to_string(),
+                Ok(
+                    serde_json::from_str(&app("B").replace("com.example.b", "com.example.e"))
+                        .unwrap(),
+                ),
+            ),
+            ("F".to_string(), Err("Failed to parse app.json".to_string())),
+        ]);
+
+        let (merged, errors) = apply_app_files(&config, &app_files);
+
+        let names: Vec<&str> = merged.apps.iter().map(|app| app.name.as_str()).collect();
+        assert_eq!(names, vec!["A", "B", "C"]);
+        assert_eq!(merged.apps[0].subtitle.as_deref(), Some("From app.json"));
+        assert_eq!(merged.apps[0].bundle_identifier, "com.example.a");
+        assert_eq!(merged.apps[0].directory.as_deref(), Some("A"));
+        assert_eq!(merged.apps[2].directory.as_deref(), Some("C"));
+
+        let failed: Vec<&str> = errors.iter().map(|err| err.directory.as_str()).collect();
+        assert_eq!(failed, vec!["D", "E", "F"]);
+    }
+
+    #[tokio::test]
+    async fn test_merged_config_cache_follows_snapshots() {
+        let config: Arc<Config> =
+            Arc::new(serde_json::from_str(&minimal_config(&app("A"))).unwrap());
+        let app_files = Arc::new(AppFiles::from([(
+            "A".to_string(),
+            app_file(r#"{"subtitle": "From app.json"}"#),
+        )]));
+        let cache = MergedConfigCache::default();
+
+        let merged = cache.get(config.clone(), app_files.clone()).await;
+        assert_eq!(
+            merged.config.apps[0].subtitle.as_deref(),
+            Some("From app.json")
+        );
+        assert!(Arc::ptr_eq(
+            &merged,
+            &cache.get(config.clone(), app_files).await
+        ));
+
+        // New app.json files are merged again
+        let merged = cache.get(config, Arc::default()).await;
+        assert_eq!(merged.config.apps[0].subtitle, None);
+    }
+
+    #[tokio::test]
+    async fn test_reload_keeps_previous_config_on_error() {
+        let dir = std::env::temp_dir().join(format!("altstore-config-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+
+        std::fs::write(&path, minimal_config(&app("A"))).unwrap();
+        let handle = ConfigHandle::new(load_config(&path).unwrap());
+
+        std::fs::write(&path, "{ not json").unwrap();
+        reload(&path, &handle).await;
+        assert_eq!(handle.snapshot().await.apps[0].name, "A");
+
+        std::fs::write(&path, minimal_config(&app("B"))).unwrap();
+        reload(&path, &handle).await;
+        assert_eq!(handle.snapshot().await.apps[0].name, "B");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
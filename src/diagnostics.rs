@@ -0,0 +1,168 @@
+use crate::config::AppFileError;
+use crate::discovery::{IpaEntry, IpaIndex, APP_FILE};
+use crate::matching::{AppMatcher, Match};
+use crate::models::Config;
+use serde::Serialize;
+
+/// Problems found with the discovered IPAs
+#[derive(Debug, Serialize)]
+pub struct Diagnostics {
+    pub issues: Vec<Issue>,
+}
+
+/// A single problem with one IPA
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Issue {
+    pub app_name: String,
+    pub filename: String,
+    pub kind: IssueKind,
+    pub message: String,
+}
+
+/// What is wrong with an IPA
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IssueKind {
+    /// The main executable is FairPlay-encrypted and cannot be sideloaded
+    Encrypted,
+    /// The IPA is filed under an app whose bundle identifier it doesn't carry
+    BundleIdMismatch,
+    /// The app directory's app.json couldn't be applied; the app keeps its config.json entry
+    InvalidAppFile,
+}
+
+impl Diagnostics {
+    /// Collects the issues of every IPA in the index and of the app.json files that
+    /// couldn't be applied, sorted by app and filename
+    pub fn collect(config: &Config, index: &IpaIndex, app_file_errors: &[AppFileError]) -> Self {
+        let matcher = AppMatcher::new(&config.apps);
+        let mut issues: Vec<Issue> = index
+            .values()
+            .flatten()
+            .flat_map(|ipa| issues_of(&matcher, ipa))
+            .chain(app_file_errors.iter().map(|err| Issue {
+                app_name: err.directory.clone(),
+                filename: APP_FILE.to_string(),
+                kind: IssueKind::InvalidAppFile,
+                message: err.message.clone(),
+            }))
+            .collect();
+
+        issues.sort_by(|a, b| (&a.app_name, &a.filename).cmp(&(&b.app_name, &b.filename)));
+
+        Self { issues }
+    }
+}
+
+/// Lists the issues of a single IPA
+fn issues_of(matcher: &AppMatcher, ipa: &IpaEntry) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    if ipa.encrypted {
+        issues.push(Issue {
+            app_name: ipa.app_name.clone(),
+            filename: ipa.filename.clone(),
+            kind: IssueKind::Encrypted,
+            message: "The main executable is FairPlay-encrypted; decrypt the IPA before publishing"
+                .to_string(),
+        });
+    }
+
+    if let Some((app, Match::Mismatch)) = matcher.app_for(ipa) {
+        issues.push(Issue {
+            app_name: ipa.app_name.clone(),
+            filename: ipa.filename.clone(),
+            kind: IssueKind::BundleIdMismatch,
+            message: format!(
+                "Bundle identifier {} does not match {} configured for {}",
+                ipa.bundle_identifier.as_deref().unwrap_or_default(),
+                app.bundle_identifier,
+                app.name
+            ),
+        });
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn ipa(app_name: &str, filename: &str, encrypted: bool) -> IpaEntry {
+        IpaEntry {
+            app_name: app_name.to_string(),
+            filename: filename.to_string(),
+            path: PathBuf::from(format!("/apps/{}/{}", app_name, filename)),
+            release_date: "2025-01-13T00:00:00Z".to_string(),
+            encrypted,
+            ..Default::default()
+        }
+    }
+
+    fn config() -> Config {
+        serde_json::from_value(serde_json::json!({
+            "name": "Repo",
+            "identifier": "com.example.repo",
+            "website": "https://example.com",
+            "tintColor": "ffffff",
+            "iconURL": "https://example.com/icon.png",
+            "sourceURL": "https://example.com",
+            "apps": [{
+                "name": "A",
+                "bundleIdentifier": "com.example.a",
+                "developerName": "Dev",
+                "localizedDescription": "",
+                "iconURL": "",
+                "tintColor": "ffffff",
+                "category": "utilities",
+                "screenshotURLs": [],
+                "appPermissions": { "entitlements": [], "privacy": {} }
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_collect_reports_bundle_id_mismatches() {
+        let mut stray = ipa("A", "Other_1.0.ipa", false);
+        stray.bundle_identifier = Some("com.example.other".to_string());
+        let mut own = ipa("A", "A_1.0.ipa", false);
+        own.bundle_identifier = Some("com.example.a".to_string());
+
+        let mut index = IpaIndex::new();
+        index.insert("A".to_string(), vec![own, stray]);
+
+        let diagnostics = Diagnostics::collect(&config(), &index, &[]);
+        assert_eq!(diagnostics.issues.len(), 1);
+        assert_eq!(diagnostics.issues[0].filename, "Other_1.0.ipa");
+        assert_eq!(diagnostics.issues[0].kind, IssueKind::BundleIdMismatch);
+    }
+
+    #[test]
+    fn test_collect_reports_encrypted_ipas() {
+        let mut index = IpaIndex::new();
+        index.insert(
+            "B".to_string(),
+            vec![ipa("B", "B_1.0.ipa", true), ipa("B", "B_2.0.ipa", false)],
+        );
+        index.insert("A".to_string(), vec![ipa("A", "A_1.0.ipa", true)]);
+
+        let diagnostics = Diagnostics::collect(&config(), &index, &[]);
+        let reported: Vec<(&str, IssueKind)> = diagnostics
+            .issues
+            .iter()
+            .map(|issue| (issue.filename.as_str(), issue.kind))
+            .collect();
+
+        assert_eq!(
+            reported,
+            vec![
+                ("A_1.0.ipa", IssueKind::Encrypted),
+                ("B_1.0.ipa", IssueKind::Encrypted)
+            ]
+        );
+    }
+}
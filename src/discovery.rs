@@ -1,38 +1,67 @@
-use crate::cache::{CachedIpaInfo, IpaCache};
+use crate::cache::{CacheKey, CachedIpaInfo, IpaCache};
 use crate::ipa_info;
+use crate::models::InjectedLibrary;
+use crate::provision::SigningInfo;
+use crate::sidecar::Sidecar;
 use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, SecondsFormat, Utc};
 use std::collections::HashMap;
 use std::fs;
+use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 use walkdir::WalkDir;
 
 /// Represents a discovered IPA file with extracted metadata
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct IpaEntry {
-    #[allow(dead_code)]
     pub app_name: String,
     pub filename: String,
     pub path: PathBuf,
     pub size: u64,
-    /// File modification date (used as version date)
-    pub modified_date: String,
+    /// Release date as an RFC 3339 timestamp (used as version date), from the sidecar,
+    /// the newest entry in the archive or the file modification time, in that order
+    pub release_date: String,
+    /// File modification time as seconds since the Unix epoch
+    pub mtime_secs: u64,
     /// Bundle identifier (e.g., "com.example.app")
-    #[allow(dead_code)]
     pub bundle_identifier: Option<String>,
     /// Bundle version (CFBundleVersion)
     pub bundle_version: Option<String>,
     /// Short version string (CFBundleShortVersionString)
     pub bundle_short_version: Option<String>,
     /// Bundle display name
-    #[allow(dead_code)]
     pub bundle_name: Option<String>,
+    /// Minimum iOS version required (MinimumOSVersion)
+    pub min_os_version: Option<String>,
+    /// Archive path of the primary app icon
+    pub icon_path: Option<String>,
+    /// Entitlement names read from the code signature
+    pub entitlements: Option<Vec<String>>,
+    /// NS*UsageDescription keys from Info.plist
+    pub privacy: Option<HashMap<String, String>>,
+    /// True if the main executable is FairPlay-encrypted; such IPAs can't be sideloaded
+    pub encrypted: bool,
+    /// Signing details from embedded.mobileprovision, if the IPA was pre-signed
+    pub signing: Option<SigningInfo>,
+    /// Tweak dylibs and frameworks loaded by the main executable
+    pub injected_libraries: Vec<InjectedLibrary>,
+    /// Release notes and overrides from the files next to the IPA
+    pub sidecar: Sidecar,
 }
 
 /// Index of all discovered IPAs, keyed by app name
 pub type IpaIndex = HashMap<String, Vec<IpaEntry>>;
 
+/// Optional per-app metadata file inside an app directory
+pub const APP_FILE: &str = "app.json";
+
+/// Top-level keys of an app directory's app.json, or why it couldn't be read
+pub type AppFile = std::result::Result<serde_json::Map<String, serde_json::Value>, String>;
+
+/// app.json files found in app directories, keyed by directory name
+pub type AppFiles = HashMap<String, AppFile>;
+
 /// Directories to skip during discovery
 const SKIP_DIRS: &[&str] = &[
     ".git", ".devenv", ".direnv", ".claude", "target", "src", ".github",
@@ -47,6 +76,11 @@ pub fn is_valid_path_component(component: &str) -> bool {
         && !component.contains('\\')
 }
 
+/// Returns true if a top-level entry of the apps directory should be scanned as an app
+pub fn is_app_directory_name(dir_name: &str) -> bool {
+    !SKIP_DIRS.contains(&dir_name)
+}
+
 /// Discovers all IPA files in app directories under the apps directory
 /// Optionally uses a cache to avoid re-extracting IPA metadata
 pub async fn discover_ipas(apps_path: &Path, cache: Option<&IpaCache>) -> Result<IpaIndex> {
@@ -83,166 +117,12 @@ pub async fn discover_ipas(apps_path: &Path, cache: Option<&IpaCache>) -> Result
         };
 
         // Skip special directories
-        if SKIP_DIRS.contains(&dir_name.as_str()) {
+        if !is_app_directory_name(&dir_name) {
             tracing::debug!("Skipping directory: {}", dir_name);
             continue;
         }
 
-        tracing::debug!("Scanning app directory: {}", dir_name);
-
-        // Scan for .ipa files in this directory (max depth 1)
-        let mut ipa_entries = Vec::new();
-
-        for ipa_entry in WalkDir::new(&path).max_depth(1) {
-            let ipa_entry = match ipa_entry {
-                Ok(e) => e,
-                Err(err) => {
-                    tracing::warn!("Failed to read entry in {}: {}", dir_name, err);
-                    continue;
-                }
-            };
-
-            let ipa_path = ipa_entry.path();
-
-            // Skip if not a file
-            if !ipa_path.is_file() {
-                continue;
-            }
-
-            // Check if it's an IPA file
-            if let Some(ext) = ipa_path.extension() {
-                if ext.eq_ignore_ascii_case("ipa") {
-                    // Get filename
-                    let filename = match ipa_path.file_name() {
-                        Some(name) => name.to_string_lossy().to_string(),
-                        None => continue,
-                    };
-
-                    // Get file size and modification date
-                    let (size, modified_date, mtime_secs) = match fs::metadata(ipa_path) {
-                        Ok(metadata) => {
-                            let size = metadata.len();
-
-                            // Get modification time and format as YYYY-MM-DD
-                            let modified_time = metadata.modified().unwrap_or(SystemTime::now());
-                            let datetime: DateTime<Utc> = modified_time.into();
-                            let date_str = datetime.format("%Y-%m-%d").to_string();
-
-                            // Get mtime as seconds since epoch for cache key
-                            let mtime_secs = modified_time
-                                .duration_since(UNIX_EPOCH)
-                                .map(|d| d.as_secs())
-                                .unwrap_or(0);
-
-                            (size, date_str, mtime_secs)
-                        }
-                        Err(err) => {
-                            tracing::warn!("Failed to get metadata for {}: {}", filename, err);
-                            continue;
-                        }
-                    };
-
-                    // Build cache key from path and modification time
-                    let cache_key = (ipa_path.to_path_buf(), mtime_secs);
-
-                    // Extract Info.plist information from IPA (with cache support)
-                    let (bundle_identifier, bundle_version, bundle_short_version, bundle_name) =
-                        if let Some(cache) = cache {
-                            // Check cache first
-                            if let Some(cached_info) = cache.get(&cache_key).await {
-                                tracing::info!("Cache hit for {}", filename);
-                                (
-                                    Some(cached_info.bundle_identifier),
-                                    Some(cached_info.bundle_version),
-                                    cached_info.bundle_short_version,
-                                    Some(cached_info.bundle_name),
-                                )
-                            } else {
-                                tracing::info!("Cache miss for {}", filename);
-                                // Extract from IPA and cache the result
-                                match ipa_info::extract_ipa_info(ipa_path) {
-                                    Ok(info) => {
-                                        tracing::info!(
-                                            "Extracted info from {}/{}: version={}, bundle_id={}",
-                                            dir_name,
-                                            filename,
-                                            info.bundle_version,
-                                            info.bundle_identifier
-                                        );
-
-                                        // Store in cache for future use
-                                        let cached_info = CachedIpaInfo {
-                                            bundle_identifier: info.bundle_identifier.clone(),
-                                            bundle_version: info.bundle_version.clone(),
-                                            bundle_short_version: info.bundle_short_version.clone(),
-                                            bundle_name: info.bundle_name.clone(),
-                                        };
-                                        cache.insert(cache_key, cached_info).await;
-
-                                        (
-                                            Some(info.bundle_identifier),
-                                            Some(info.bundle_version),
-                                            info.bundle_short_version,
-                                            Some(info.bundle_name),
-                                        )
-                                    }
-                                    Err(err) => {
-                                        tracing::warn!(
-                                            "Failed to extract Info.plist from {}/{}: {}",
-                                            dir_name,
-                                            filename,
-                                            err
-                                        );
-                                        (None, None, None, None)
-                                    }
-                                }
-                            }
-                        } else {
-                            // No cache provided, extract directly
-                            match ipa_info::extract_ipa_info(ipa_path) {
-                                Ok(info) => {
-                                    tracing::info!(
-                                        "Extracted info from {}/{}: version={}, bundle_id={}",
-                                        dir_name,
-                                        filename,
-                                        info.bundle_version,
-                                        info.bundle_identifier
-                                    );
-                                    (
-                                        Some(info.bundle_identifier),
-                                        Some(info.bundle_version),
-                                        info.bundle_short_version,
-                                        Some(info.bundle_name),
-                                    )
-                                }
-                                Err(err) => {
-                                    tracing::warn!(
-                                        "Failed to extract Info.plist from {}/{}: {}",
-                                        dir_name,
-                                        filename,
-                                        err
-                                    );
-                                    (None, None, None, None)
-                                }
-                            }
-                        };
-
-                    tracing::info!("Discovered IPA: {}/{} ({} bytes)", dir_name, filename, size);
-
-                    ipa_entries.push(IpaEntry {
-                        app_name: dir_name.clone(),
-                        filename,
-                        path: ipa_path.to_path_buf(),
-                        size,
-                        modified_date,
-                        bundle_identifier,
-                        bundle_version,
-                        bundle_short_version,
-                        bundle_name,
-                    });
-                }
-            }
-        }
+        let ipa_entries = scan_app_directory(&path, &dir_name, cache).await;
 
         // Add to index if we found any IPAs
         if !ipa_entries.is_empty() {
@@ -260,6 +140,252 @@ pub async fn discover_ipas(apps_path: &Path, cache: Option<&IpaCache>) -> Result
     Ok(index)
 }
 
+/// Loads the app.json of every app directory under the apps directory
+pub fn discover_app_files(apps_path: &Path) -> AppFiles {
+    let entries = match fs::read_dir(apps_path) {
+        Ok(entries) => entries,
+        Err(err) => {
+            tracing::warn!("Failed to read apps directory: {}", err);
+            return AppFiles::new();
+        }
+    };
+
+    entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let dir_name = entry.file_name().to_string_lossy().to_string();
+            if !is_app_directory_name(&dir_name) {
+                return None;
+            }
+            let app_file = load_app_file(&entry.path(), &dir_name)?;
+            Some((dir_name, app_file))
+        })
+        .collect()
+}
+
+/// Loads the app.json of an app directory, None if it has none or isn't a directory
+/// The file only has to be a JSON object here; it is checked against config.json when applied
+pub fn load_app_file(path: &Path, dir_name: &str) -> Option<AppFile> {
+    let app_file = match fs::read_to_string(path.join(APP_FILE)) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|err| format!("Failed to parse {}: {}", APP_FILE, err)),
+        // Loose files in the apps directory (README.md, .DS_Store) are not apps
+        Err(err) if matches!(err.kind(), ErrorKind::NotFound | ErrorKind::NotADirectory) => {
+            return None
+        }
+        Err(err) => Err(format!("Failed to read {}: {}", APP_FILE, err)),
+    };
+
+    match app_file {
+        Ok(_) => tracing::info!("Loaded app metadata from {}/{}", dir_name, APP_FILE),
+        Err(ref err) => tracing::warn!("Ignoring {}/{}: {}", dir_name, APP_FILE, err),
+    }
+    Some(app_file)
+}
+
+/// Scans a single app directory for .ipa files (max depth 1)
+/// Returns an empty list if the directory no longer exists
+pub async fn scan_app_directory(
+    path: &Path,
+    dir_name: &str,
+    cache: Option<&IpaCache>,
+) -> Vec<IpaEntry> {
+    tracing::debug!("Scanning app directory: {}", dir_name);
+
+    let mut ipa_entries = Vec::new();
+
+    if !path.is_dir() {
+        return ipa_entries;
+    }
+
+    for ipa_entry in WalkDir::new(path).max_depth(1) {
+        let ipa_entry = match ipa_entry {
+            Ok(e) => e,
+            Err(err) => {
+                tracing::warn!("Failed to read entry in {}: {}", dir_name, err);
+                continue;
+            }
+        };
+
+        let ipa_path = ipa_entry.path();
+
+        // Skip if not a file
+        if !ipa_path.is_file() {
+            continue;
+        }
+
+        // Check if it's an IPA file
+        let is_ipa = ipa_path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("ipa"));
+        if !is_ipa {
+            continue;
+        }
+
+        // Get filename
+        let filename = match ipa_path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => continue,
+        };
+
+        // Get file size and modification date
+        let (size, modified_date, mtime_secs) = match fs::metadata(ipa_path) {
+            Ok(metadata) => {
+                let size = metadata.len();
+
+                let modified_time = metadata.modified().unwrap_or(SystemTime::now());
+                let modified_date: DateTime<Utc> = modified_time.into();
+
+                // Get mtime as seconds since epoch for cache key
+                let mtime_secs = modified_time
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+
+                (size, modified_date, mtime_secs)
+            }
+            Err(err) => {
+                tracing::warn!("Failed to get metadata for {}: {}", filename, err);
+                continue;
+            }
+        };
+
+        // Build cache key from path and modification time
+        let cache_key = (ipa_path.to_path_buf(), mtime_secs);
+
+        // Extract Info.plist information from IPA (with cache support)
+        let info = read_ipa_info(ipa_path, cache_key, cache, dir_name, &filename).await;
+
+        tracing::info!("Discovered IPA: {}/{} ({} bytes)", dir_name, filename, size);
+
+        if info.as_ref().is_some_and(|i| i.encrypted) {
+            tracing::warn!(
+                "{}/{} is FairPlay-encrypted and cannot be sideloaded; decrypt it before publishing",
+                dir_name,
+                filename
+            );
+        }
+
+        // A date pinned in the sidecar wins, then the build date recorded in the archive;
+        // both survive copying the IPA between machines, unlike the mtime
+        let sidecar = match Sidecar::load(ipa_path) {
+            Ok(sidecar) => sidecar.unwrap_or_default(),
+            Err(err) => {
+                tracing::warn!("Ignoring sidecar of {}/{}: {:#}", dir_name, filename, err);
+                Sidecar::default()
+            }
+        };
+        let release_date = sidecar
+            .date
+            .or_else(|| info.as_ref().and_then(|i| i.archive_date))
+            .unwrap_or(modified_date)
+            .to_rfc3339_opts(SecondsFormat::Secs, true);
+
+        ipa_entries.push(IpaEntry {
+            app_name: dir_name.to_string(),
+            filename,
+            path: ipa_path.to_path_buf(),
+            size,
+            release_date,
+            mtime_secs,
+            bundle_identifier: info.as_ref().map(|i| i.bundle_identifier.clone()),
+            bundle_version: info.as_ref().map(|i| i.bundle_version.clone()),
+            bundle_short_version: info.as_ref().and_then(|i| i.bundle_short_version.clone()),
+            bundle_name: info.as_ref().map(|i| i.bundle_name.clone()),
+            min_os_version: info.as_ref().and_then(|i| i.min_os_version.clone()),
+            icon_path: info.as_ref().and_then(|i| i.icon_path.clone()),
+            entitlements: info.as_ref().and_then(|i| i.entitlements.clone()),
+            privacy: info.as_ref().map(|i| i.privacy.clone()),
+            encrypted: info.as_ref().is_some_and(|i| i.encrypted),
+            signing: info.as_ref().and_then(|i| i.signing.clone()),
+            injected_libraries: info.map(|i| i.injected_libraries).unwrap_or_default(),
+            sidecar,
+        });
+    }
+
+    ipa_entries
+}
+
+/// Reads IPA metadata, consulting the cache first when one is provided
+async fn read_ipa_info(
+    ipa_path: &Path,
+    cache_key: CacheKey,
+    cache: Option<&IpaCache>,
+    dir_name: &str,
+    filename: &str,
+) -> Option<CachedIpaInfo> {
+    if let Some(cache) = cache {
+        // Check cache first
+        if let Some(cached_info) = cache.get(&cache_key).await {
+            tracing::info!("Cache hit for {}", filename);
+            return Some(cached_info);
+        }
+        tracing::info!("Cache miss for {}", filename);
+    }
+
+    // Unzipping is blocking I/O over potentially large files;
+    // run it on the blocking pool so request handling isn't stalled
+    let path = ipa_path.to_path_buf();
+    let info = match tokio::task::spawn_blocking(move || ipa_info::extract_ipa_info(&path)).await {
+        Ok(info) => info,
+        Err(err) => {
+            tracing::error!(
+                "IPA extraction task failed for {}/{}: {}",
+                dir_name,
+                filename,
+                err
+            );
+            return None;
+        }
+    };
+
+    match info {
+        Ok(info) => {
+            tracing::info!(
+                "Extracted info from {}/{}: version={}, bundle_id={}",
+                dir_name,
+                filename,
+                info.bundle_version,
+                info.bundle_identifier
+            );
+
+            let cached_info = CachedIpaInfo {
+                bundle_identifier: info.bundle_identifier,
+                bundle_version: info.bundle_version,
+                bundle_short_version: info.bundle_short_version,
+                bundle_name: info.bundle_name,
+                min_os_version: info.min_os_version,
+                // Hashed in the background by the indexer
+                sha256: None,
+                icon_path: info.icon_path,
+                entitlements: info.entitlements,
+                privacy: info.privacy,
+                encrypted: info.encrypted,
+                signing: info.signing,
+                injected_libraries: info.injected_libraries,
+                archive_date: info.archive_date,
+            };
+
+            // Store in cache for future use
+            if let Some(cache) = cache {
+                cache.insert(cache_key, cached_info.clone()).await;
+            }
+
+            Some(cached_info)
+        }
+        Err(err) => {
+            tracing::warn!(
+                "Failed to extract Info.plist from {}/{}: {}",
+                dir_name,
+                filename,
+                err
+            );
+            None
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -279,4 +405,23 @@ mod tests {
         assert!(!is_valid_path_component("app/name"));
         assert!(!is_valid_path_component("app\\name"));
     }
+
+    #[test]
+    fn test_load_app_file() {
+        let dir = std::env::temp_dir().join(format!("app-file-test-{}", std::process::id()));
+        let app_dir = dir.join("App");
+        fs::create_dir_all(&app_dir).unwrap();
+        fs::write(dir.join("README.md"), "# Apps").unwrap();
+
+        assert!(load_app_file(&app_dir, "App").is_none());
+        // A loose file is not an app directory with a broken app.json
+        assert!(load_app_file(&dir.join("README.md"), "README.md").is_none());
+
+        fs::write(app_dir.join(APP_FILE), "{").unwrap();
+        assert!(matches!(load_app_file(&app_dir, "App"), Some(Err(_))));
+        fs::write(app_dir.join(APP_FILE), r#"{"name": "App"}"#).unwrap();
+        assert!(matches!(load_app_file(&app_dir, "App"), Some(Ok(_))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -1,23 +1,100 @@
-use crate::discovery::IpaIndex;
-use crate::models::{AppVersion, Config, Repository};
-use crate::token::generate_download_token;
+use crate::config::MergedConfig;
+use crate::discovery::{IpaEntry, IpaIndex};
+use crate::indexer::{Digests, IndexSnapshot};
+use crate::matching::{AppMatcher, Match};
+use crate::models::{
+    AppConfig, AppPermissions, AppVersion, AutoPublish, Config, DuplicatePolicy, InjectedLibrary,
+    IpaSource, MismatchPolicy, Repository, VersionOrder,
+};
+use crate::pattern::FilenamePattern;
+use crate::token::UrlSigning;
+use crate::version::compare_versions;
 use anyhow::{Context, Result};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::Mutex;
 
-/// Generates a repository from config and discovered IPAs
-pub fn generate_repository(
+/// Catalog built from the latest merged config and index snapshots
+type CatalogEntry = (
+    Arc<MergedConfig>,
+    Arc<IndexSnapshot>,
+    Result<Arc<Repository>, String>,
+);
+
+/// The catalog of the latest config and index snapshots
+/// Building it logs every matching problem, so caching it means each is logged once per
+/// change instead of on every repository.json poll
+#[derive(Clone, Default)]
+pub struct CatalogCache {
+    inner: Arc<Mutex<Option<CatalogEntry>>>,
+}
+
+impl CatalogCache {
+    /// Returns the catalog, building it again if either snapshot was replaced
+    pub async fn get(
+        &self,
+        config: Arc<MergedConfig>,
+        index: Arc<IndexSnapshot>,
+        publish_encrypted: bool,
+    ) -> Result<Arc<Repository>, String> {
+        let mut cached = self.inner.lock().await;
+        if let Some((ref cached_config, ref cached_index, ref catalog)) = *cached {
+            if Arc::ptr_eq(cached_config, &config) && Arc::ptr_eq(cached_index, &index) {
+                return catalog.clone();
+            }
+        }
+
+        let catalog = build_catalog(config.config.clone(), &index.ipas, publish_encrypted)
+            .map(Arc::new)
+            .map_err(|err| {
+                tracing::error!("Failed to generate repository: {:#}", err);
+                format!("{:#}", err)
+            });
+        *cached = Some((config, index, catalog.clone()));
+        catalog
+    }
+}
+
+/// Builds the request-independent part of the repository from config and discovered IPAs
+/// Discovered versions remember their IPA; `render_repository` turns it into a download URL
+pub fn build_catalog(
     config: Config,
     ipa_index: &IpaIndex,
-    base_url: &str,
-    download_secret: Option<&str>,
-    auth_token: Option<&str>,
+    publish_encrypted: bool,
 ) -> Result<Repository> {
     let mut repo = config;
+    let global_patterns = repo.filename_patterns.clone();
+
+    // IPAs that match no configured app are either published under a synthesized entry or logged
+    let synthesized = unmatched_apps(&repo, ipa_index);
+    repo.apps.extend(synthesized);
+
+    // Match IPAs to apps up front, by bundle identifier or directory
+    let matcher = AppMatcher::new(&repo.apps);
+    let app_ipas: Vec<Vec<&IpaEntry>> = repo
+        .apps
+        .iter()
+        .map(|app| {
+            matcher
+                .ipas_for(app, ipa_index)
+                .into_iter()
+                .filter(|(ipa, matched)| {
+                    *matched == Match::Exact || publish_mismatch(repo.bundle_id_mismatch, app, ipa)
+                })
+                .map(|(ipa, _)| ipa)
+                .collect()
+        })
+        .collect();
 
     // For each app in the config, populate versions from discovered IPAs
-    for app in &mut repo.apps {
-        // Find matching IPAs in the index
-        // Match by the app's directory name (derived from app name)
-        let app_dir_name = get_app_directory_name(&app.name);
+    for (app, ipas) in repo.apps.iter_mut().zip(app_ipas) {
+        // The app's own filename patterns are tried before the global ones
+        let patterns: Vec<&FilenamePattern> = app
+            .filename_patterns
+            .iter()
+            .chain(&global_patterns)
+            .collect();
 
         // Keep existing manual versions from config.json
         let manual_versions = std::mem::take(&mut app.versions);
@@ -25,10 +102,20 @@ pub fn generate_repository(
         // Generate versions from discovered IPAs
         let mut discovered_versions = Vec::new();
 
-        if let Some(ipas) = ipa_index.get(&app_dir_name) {
+        if !ipas.is_empty() {
             tracing::debug!("Found {} IPAs for app {}", ipas.len(), app.name);
 
-            for ipa in ipas {
+            for ipa in ipas.iter().copied() {
+                // FairPlay-encrypted IPAs fail to install, so they are left out unless forced
+                if ipa.encrypted && !publish_encrypted {
+                    tracing::debug!("Skipping encrypted IPA {}", ipa.filename);
+                    continue;
+                }
+                if ipa.sidecar.hidden {
+                    tracing::info!("Hiding {} as requested by its sidecar", ipa.filename);
+                    continue;
+                }
+
                 // Try to get version from Info.plist first, fall back to filename parsing
                 let version_info = if let Some(ref bundle_version) = ipa.bundle_version {
                     // Prefer CFBundleShortVersionString (user-facing) over CFBundleVersion (build number)
@@ -38,20 +125,37 @@ pub fn generate_repository(
                         .unwrap_or(bundle_version)
                         .clone();
 
-                    let description = if let Some(ref short_ver) = ipa.bundle_short_version {
-                        if short_ver != bundle_version {
-                            format!("Version {} (build {})", short_ver, bundle_version)
-                        } else {
-                            format!("Version {}", version)
-                        }
-                    } else {
-                        format!("Version {}", version)
+                    // The tweak version and channel only exist in the filename. A configured
+                    // pattern names them explicitly; the underscore convention is only trusted
+                    // when the version it finds agrees with Info.plist and the tweak looks like a
+                    // version, so My_App_1.0.ipa and App_1.0_fixed.ipa get no tweak
+                    let (tweak, channel) = match patterns
+                        .iter()
+                        .find_map(|pattern| pattern.captures(&ipa.filename))
+                    {
+                        Some(captures) => (captures.tweak, captures.channel),
+                        None => parse_version_from_filename(&ipa.filename, &ipa.release_date, &[])
+                            .ok()
+                            .filter(|info| {
+                                info.version == version || info.version == *bundle_version
+                            })
+                            .filter(|info| {
+                                info.tweak.as_deref().is_none_or(|tweak| {
+                                    tweak.contains(|c: char| c.is_ascii_digit())
+                                })
+                            })
+                            .map(|info| (info.tweak, info.channel))
+                            .unwrap_or_default(),
                     };
 
+                    // CFBundleVersion is published as its own buildVersion field
                     Ok(VersionInfo {
+                        description: describe_version(&version, tweak.as_deref()),
                         version,
-                        date: ipa.modified_date.clone(),
-                        description,
+                        build: Some(bundle_version.clone()),
+                        tweak,
+                        channel,
+                        date: ipa.release_date.clone(),
                     })
                 } else {
                     // Fallback to filename parsing if Info.plist extraction failed
@@ -59,44 +163,48 @@ pub fn generate_repository(
                         "No version info from Info.plist for {}, trying filename parsing",
                         ipa.filename
                     );
-                    parse_version_from_filename(&ipa.filename, &ipa.modified_date)
+                    parse_version_from_filename(&ipa.filename, &ipa.release_date, &patterns)
                 };
 
                 match version_info {
                     Ok(version_info) => {
-                        // Generate download URL - use obfuscated token if secret is configured
-                        let download_url = if download_secret.is_some() {
-                            // Obfuscated URLs don't need auth token - the obfuscation itself is the auth
-                            let token = generate_download_token(
-                                &app_dir_name,
-                                &ipa.filename,
-                                download_secret,
-                            );
-                            format!("{}/download/{}", base_url.trim_end_matches('/'), token)
-                        } else {
-                            // Standard URLs need auth token appended if configured
-                            let mut url = format!(
-                                "{}/apps/{}/{}",
-                                base_url.trim_end_matches('/'),
-                                app_dir_name,
-                                ipa.filename
-                            );
-
-                            if let Some(token) = auth_token {
-                                url.push_str("?token=");
-                                url.push_str(token);
-                            }
-
-                            url
-                        };
-
-                        discovered_versions.push(AppVersion {
-                            version: version_info.version,
-                            date: version_info.date,
-                            localized_description: version_info.description,
-                            download_url,
-                            size: ipa.size,
-                        });
+                        discovered_versions.push((
+                            AppVersion {
+                                version: version_info.version,
+                                build_version: version_info.build,
+                                date: version_info.date,
+                                // Release notes and OS requirements from a sidecar win over the IPA's
+                                localized_description: describe_injected_libraries(
+                                    ipa.sidecar
+                                        .localized_description
+                                        .clone()
+                                        .unwrap_or(version_info.description),
+                                    &ipa.injected_libraries,
+                                ),
+                                // Minted per request by render_repository
+                                download_url: String::new(),
+                                ipa: Some(IpaSource {
+                                    app_name: ipa.app_name.clone(),
+                                    filename: ipa.filename.clone(),
+                                    key: (ipa.path.clone(), ipa.mtime_secs),
+                                }),
+                                size: ipa.size,
+                                // Known once hashed, filled in by render_repository
+                                sha256: None,
+                                min_os_version: ipa
+                                    .sidecar
+                                    .min_os_version
+                                    .clone()
+                                    .or_else(|| ipa.min_os_version.clone()),
+                                max_os_version: ipa.sidecar.max_os_version.clone(),
+                                injected_libraries: ipa.injected_libraries.clone(),
+                                tweak_version: version_info.tweak,
+                                channel: version_info
+                                    .channel
+                                    .or_else(|| ipa.sidecar.beta.then(|| "beta".to_string())),
+                            },
+                            ipa,
+                        ));
                     }
                     Err(err) => {
                         tracing::warn!("Failed to get version info for {}: {}", ipa.filename, err);
@@ -105,36 +213,404 @@ pub fn generate_repository(
             }
         } else {
             tracing::warn!(
-                "No IPAs found for app {} (directory: {})",
+                "No IPAs found for app {} (bundle identifier: {}, directory: {})",
                 app.name,
-                app_dir_name
+                app.bundle_identifier,
+                app.directory.as_deref().unwrap_or(&app.name)
+            );
+        }
+
+        // Serve the icon from the IPA when config.json doesn't provide one
+        if app.icon_url.is_empty() {
+            // Icons are served per directory, from the newest IPA that has one
+            let icon_directory = ipas
+                .iter()
+                .filter(|ipa| ipa.icon_path.is_some())
+                .max_by_key(|ipa| ipa.mtime_secs)
+                .map(|ipa| ipa.app_name.as_str());
+
+            app.icon_directory = icon_directory.map(str::to_string);
+        }
+
+        // The newest IPA's code signature is the source of truth for entitlements
+        let signed_entitlements = ipas
+            .iter()
+            .filter(|ipa| ipa.entitlements.is_some())
+            .max_by_key(|ipa| ipa.mtime_secs)
+            .and_then(|ipa| ipa.entitlements.as_deref());
+
+        if let Some(entitlements) = signed_entitlements {
+            reconcile_entitlements(
+                &app.name,
+                &mut app.app_permissions.entitlements,
+                entitlements,
             );
         }
 
+        // Usage descriptions come from the newest IPA's Info.plist, config.json wins on conflicts
+        let extracted_privacy = ipas
+            .iter()
+            .filter(|ipa| ipa.privacy.is_some())
+            .max_by_key(|ipa| ipa.mtime_secs)
+            .and_then(|ipa| ipa.privacy.as_ref());
+
+        if let Some(privacy) = extracted_privacy {
+            merge_privacy(&app.name, &mut app.app_permissions.privacy, privacy);
+        }
+
+        // Several IPAs resolving to the same version would otherwise shadow each other
+        let discovered_versions =
+            resolve_duplicates(&app.name, repo.duplicate_versions, discovered_versions)?;
+
         // Merge versions: manual versions take precedence over discovered ones
-        app.versions = merge_versions(manual_versions, discovered_versions, auth_token);
+        app.versions = merge_versions(manual_versions, discovered_versions, app.version_order);
+    }
+
+    Ok(repo)
+}
+
+/// Fills in the parts of a catalog that change without a rebuild: download and icon URLs,
+/// sourceURL and the digests the background hasher has computed so far
+pub fn render_repository(
+    catalog: &Repository,
+    digests: &Digests,
+    base_url: &str,
+    signing: Option<&UrlSigning>,
+    auth_token: Option<&str>,
+) -> Repository {
+    let base_url = base_url.trim_end_matches('/');
+    let with_token = |mut url: String| {
+        if let Some(token) = auth_token {
+            url.push_str("?token=");
+            url.push_str(token);
+        }
+        url
+    };
+
+    let mut repo = catalog.clone();
+    for app in &mut repo.apps {
+        if let Some(ref directory) = app.icon_directory {
+            app.icon_url = with_token(format!("{}/icons/{}.png", base_url, directory));
+        }
+
+        for version in &mut app.versions {
+            match version.ipa {
+                // Signed URLs don't need the auth token, the signature itself is the auth
+                Some(ref ipa) => {
+                    version.sha256 = digests.get(&ipa.key).cloned();
+                    version.download_url = match signing {
+                        Some(signing) => format!(
+                            "{}{}",
+                            base_url,
+                            signing.download_path(&ipa.app_name, &ipa.filename)
+                        ),
+                        None => with_token(format!(
+                            "{}/apps/{}/{}",
+                            base_url, ipa.app_name, ipa.filename
+                        )),
+                    }
+                }
+                // Manual versions without a matching IPA get the token unless their URL
+                // already has a query or is obfuscated (/download/ URLs carry their own auth)
+                None => {
+                    if !version.download_url.contains('?')
+                        && !version.download_url.contains("/download/")
+                    {
+                        version.download_url =
+                            with_token(std::mem::take(&mut version.download_url));
+                    }
+                }
+            }
+        }
     }
 
     // Set sourceURL to the root endpoint
-    repo.source_url = base_url.trim_end_matches('/').to_string();
+    repo.source_url = base_url.to_string();
 
-    Ok(repo)
+    repo
+}
+
+/// Applies the bundle identifier mismatch policy to an IPA filed under the wrong app
+fn publish_mismatch(policy: MismatchPolicy, app: &AppConfig, ipa: &IpaEntry) -> bool {
+    let bundle_id = ipa.bundle_identifier.as_deref().unwrap_or_default();
+
+    match policy {
+        MismatchPolicy::Warn => {
+            tracing::warn!(
+                "{}/{} has bundle identifier {} but {} expects {}; publishing it anyway",
+                ipa.app_name,
+                ipa.filename,
+                bundle_id,
+                app.name,
+                app.bundle_identifier
+            );
+            true
+        }
+        MismatchPolicy::Exclude => {
+            tracing::warn!(
+                "{}/{} has bundle identifier {} but {} expects {}; leaving it out",
+                ipa.app_name,
+                ipa.filename,
+                bundle_id,
+                app.name,
+                app.bundle_identifier
+            );
+            false
+        }
+        MismatchPolicy::Publish => true,
+    }
+}
+
+/// Builds minimal app entries for the app directories whose IPAs match no configured app
+/// Returns nothing unless autoPublish is enabled; the unmatched directories are logged either way
+fn unmatched_apps(config: &Config, ipa_index: &IpaIndex) -> Vec<AppConfig> {
+    let matcher = AppMatcher::new(&config.apps);
+    let mut directories: Vec<(&String, &Vec<IpaEntry>)> = Vec::new();
+    for (directory, ipas) in ipa_index {
+        let unmatched: Vec<&IpaEntry> = ipas
+            .iter()
+            .filter(|ipa| matcher.app_for(ipa).is_none())
+            .collect();
+
+        if unmatched.len() == ipas.len() {
+            directories.push((directory, ipas));
+            continue;
+        }
+
+        // Stray IPAs next to another app's are never published, not even by autoPublish
+        for ipa in unmatched {
+            tracing::warn!(
+                "{}/{} matches no app in config.json and is not published",
+                directory,
+                ipa.filename
+            );
+        }
+    }
+    directories.sort_by_key(|(directory, _)| directory.as_str());
+
+    let Some(ref settings) = config.auto_publish else {
+        for (directory, ipas) in directories {
+            tracing::warn!(
+                "{} IPAs in {} match no app in config.json; add an entry or enable autoPublish",
+                ipas.len(),
+                directory
+            );
+        }
+        return Vec::new();
+    };
+
+    // Synthesized names must not clash with configured apps nor with each other
+    let mut taken: HashSet<String> = config.apps.iter().map(|app| app.name.clone()).collect();
+
+    directories
+        .into_iter()
+        .filter_map(|(directory, ipas)| {
+            let app = synthesize_app(config, settings, directory, ipas, &taken);
+            if let Some(ref app) = app {
+                taken.insert(app.name.clone());
+            }
+            match app {
+                Some(ref app) => tracing::info!(
+                    "Auto-publishing {} IPAs in {} as {} ({})",
+                    ipas.len(),
+                    directory,
+                    app.name,
+                    app.bundle_identifier
+                ),
+                None => tracing::warn!(
+                    "Cannot auto-publish {}: none of its IPAs has a readable bundle identifier",
+                    directory
+                ),
+            }
+            app
+        })
+        .collect()
+}
+
+/// Synthesizes an app entry from the newest IPA of a directory
+fn synthesize_app(
+    config: &Config,
+    settings: &AutoPublish,
+    directory: &str,
+    ipas: &[IpaEntry],
+    taken: &HashSet<String>,
+) -> Option<AppConfig> {
+    let newest = ipas
+        .iter()
+        .filter(|ipa| ipa.bundle_identifier.is_some())
+        .max_by_key(|ipa| ipa.mtime_secs)?;
+
+    // Names must stay unique: a taken bundle name falls back to the directory,
+    // and a taken directory name gets a numeric suffix
+    let name = newest
+        .bundle_name
+        .iter()
+        .chain([&directory.to_string()])
+        .find(|name| !taken.contains(*name))
+        .cloned()
+        .unwrap_or_else(|| {
+            (2..)
+                .map(|n| format!("{} ({})", directory, n))
+                .find(|name| !taken.contains(name))
+                .unwrap_or_default()
+        });
+
+    Some(AppConfig {
+        beta: None,
+        name,
+        bundle_identifier: newest.bundle_identifier.clone()?,
+        developer_name: newest
+            .signing
+            .as_ref()
+            .and_then(|signing| signing.team_name.clone())
+            .unwrap_or_else(|| "Unknown".to_string()),
+        subtitle: None,
+        localized_description: String::new(),
+        // Filled in from the IPA by build_catalog
+        icon_url: String::new(),
+        icon_directory: None,
+        tint_color: settings
+            .tint_color
+            .clone()
+            .unwrap_or_else(|| config.tint_color.clone()),
+        category: settings
+            .category
+            .clone()
+            .unwrap_or_else(|| "other".to_string()),
+        screenshot_urls: Vec::new(),
+        app_permissions: AppPermissions {
+            entitlements: Vec::new(),
+            privacy: HashMap::new(),
+        },
+        versions: Vec::new(),
+        version_order: VersionOrder::default(),
+        filename_patterns: Vec::new(),
+        directory: Some(directory.to_string()),
+    })
+}
+
+/// Replaces the configured entitlements with the ones read from the IPA
+/// Differences are logged so stale entries in config.json can be cleaned up
+fn reconcile_entitlements(app_name: &str, configured: &mut Vec<String>, signed: &[String]) {
+    if !configured.is_empty() {
+        let missing: Vec<&str> = configured
+            .iter()
+            .filter(|e| !signed.contains(e))
+            .map(String::as_str)
+            .collect();
+        let undeclared: Vec<&str> = signed
+            .iter()
+            .filter(|e| !configured.contains(e))
+            .map(String::as_str)
+            .collect();
+
+        if !missing.is_empty() || !undeclared.is_empty() {
+            tracing::warn!(
+                "Entitlements in config.json for {} differ from the IPA's code signature; \
+                 publishing the signed ones (not signed: [{}], missing from config: [{}])",
+                app_name,
+                missing.join(", "),
+                undeclared.join(", ")
+            );
+        }
+    }
+
+    *configured = signed.to_vec();
+}
+
+/// Adds the usage descriptions found in the IPA to the configured ones
+/// Configured strings are kept as-is; keys the IPA no longer declares are logged
+fn merge_privacy(
+    app_name: &str,
+    configured: &mut HashMap<String, String>,
+    extracted: &HashMap<String, String>,
+) {
+    let mut unused: Vec<&str> = configured
+        .keys()
+        .filter(|key| !extracted.contains_key(*key))
+        .map(String::as_str)
+        .collect();
+
+    if !unused.is_empty() {
+        unused.sort_unstable();
+        tracing::warn!(
+            "Privacy keys in config.json for {} are not declared by the IPA's Info.plist: [{}]",
+            app_name,
+            unused.join(", ")
+        );
+    }
+
+    for (key, description) in extracted {
+        configured
+            .entry(key.clone())
+            .or_insert_with(|| description.clone());
+    }
+}
+
+/// Appends the list of injected libraries to a version description
+/// e.g. "Version 1.0\n\nIncludes: Orion.framework, YTLite.dylib 5.2.1"
+fn describe_injected_libraries(description: String, libraries: &[InjectedLibrary]) -> String {
+    if libraries.is_empty() {
+        return description;
+    }
+
+    let names: Vec<String> = libraries
+        .iter()
+        .map(|library| match library.version {
+            Some(ref version) => format!("{} {}", library.name, version),
+            None => library.name.clone(),
+        })
+        .collect();
+
+    format!("{}\n\nIncludes: {}", description, names.join(", "))
 }
 
 /// Parsed version information from filename
 #[derive(Debug)]
 struct VersionInfo {
     version: String,
+    build: Option<String>,
+    /// Version of the tweak injected into the app, from the filename
+    tweak: Option<String>,
+    /// Release channel, only available from filename patterns
+    channel: Option<String>,
     date: String,
     description: String,
 }
 
 /// Parse version information from IPA filename
-/// Expected format: AppName_x.y.z_a.b.c.ipa or similar patterns
+/// Configured patterns are tried first, in order; without a match the built-in
+/// AppName_x.y.z_a.b.c.ipa or similar shapes are used
 /// Examples:
 /// - YourApp_v1.2.3.ipa -> version "20.26.7"
 /// - MyApp_1.2.3.ipa -> version "1.2.3"
-fn parse_version_from_filename(filename: &str, file_date: &str) -> Result<VersionInfo> {
+fn parse_version_from_filename(
+    filename: &str,
+    file_date: &str,
+    patterns: &[&FilenamePattern],
+) -> Result<VersionInfo> {
+    if let Some(captures) = patterns
+        .iter()
+        .find_map(|pattern| pattern.captures(filename))
+    {
+        return Ok(VersionInfo {
+            description: describe_version(&captures.version, captures.tweak.as_deref()),
+            version: captures.version,
+            build: captures.build,
+            tweak: captures.tweak,
+            channel: captures.channel,
+            date: file_date.to_string(),
+        });
+    }
+
+    if !patterns.is_empty() {
+        tracing::warn!(
+            "None of the {} configured filename patterns matched {}, using the default AppName_[tweak_]version.ipa parsing",
+            patterns.len(),
+            filename
+        );
+    }
+
     // Remove .ipa extension
     let name = filename
         .strip_suffix(".ipa")
@@ -163,97 +639,499 @@ fn parse_version_from_filename(filename: &str, file_date: &str) -> Result<Versio
         }
     };
 
-    // Generate description based on available info
-    let description = match tweak_version {
-        Some(tweak) => format!("Version {} (tweak version: {})", version, tweak),
-        None => format!("Version {}", version),
-    };
-
     Ok(VersionInfo {
+        description: describe_version(&version, tweak_version.as_deref()),
         version,
+        build: None,
+        tweak: tweak_version,
+        channel: None,
         date: file_date.to_string(),
-        description,
     })
 }
 
-/// Get the directory name for an app based on its name
-/// This is a simple mapping that can be customized
-fn get_app_directory_name(app_name: &str) -> String {
-    // For now, just use the app name as-is
-    // In the future, this could use a mapping from config
-    app_name.to_string()
+/// Generate a version description based on available info
+fn describe_version(version: &str, tweak: Option<&str>) -> String {
+    match tweak {
+        Some(tweak) => format!("Version {} (tweak version: {})", version, tweak),
+        None => format!("Version {}", version),
+    }
+}
+
+/// Identifies a version entry: marketing version plus optional build number and tweak version
+type VersionKey = (String, Option<String>, Option<String>);
+
+fn version_key(version: &AppVersion) -> VersionKey {
+    (
+        version.version.clone(),
+        version.build_version.clone(),
+        version.tweak_version.clone(),
+    )
+}
+
+/// Applies the duplicate version policy to the versions discovered for an app
+/// Discovery order is kept, so manual entries still match the first discovered build
+fn resolve_duplicates(
+    app_name: &str,
+    policy: DuplicatePolicy,
+    discovered: Vec<(AppVersion, &IpaEntry)>,
+) -> Result<Vec<AppVersion>> {
+    let mut order = Vec::new();
+    let mut groups: HashMap<VersionKey, Vec<(AppVersion, &IpaEntry)>> = HashMap::new();
+    for (version, ipa) in discovered {
+        let key = version_key(&version);
+        if !groups.contains_key(&key) {
+            order.push(key.clone());
+        }
+        groups.entry(key).or_default().push((version, ipa));
+    }
+
+    let mut resolved = Vec::new();
+    for key in order {
+        let mut group = groups.remove(&key).unwrap_or_default();
+        if group.len() < 2 {
+            resolved.extend(group.into_iter().map(|(version, _)| version));
+            continue;
+        }
+
+        // Newest first; ties are broken by filename so the outcome doesn't depend on discovery order
+        group.sort_by(|(_, a), (_, b)| {
+            b.mtime_secs
+                .cmp(&a.mtime_secs)
+                .then_with(|| a.filename.cmp(&b.filename))
+        });
+        let label = match key.1 {
+            Some(ref build) => format!("{} (build {})", key.0, build),
+            None => key.0.clone(),
+        };
+        let filenames = group
+            .iter()
+            .map(|(_, ipa)| ipa.filename.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        match policy {
+            DuplicatePolicy::Newest => {
+                let (newest, ipa) = group.swap_remove(0);
+                tracing::warn!(
+                    "App {} has several IPAs for version {} ({}): publishing the newest, {}",
+                    app_name,
+                    label,
+                    filenames,
+                    ipa.filename
+                );
+                resolved.push(newest);
+            }
+            DuplicatePolicy::Disambiguate => {
+                // The newest IPA keeps the version, older ones count up from -1 by age
+                let count = group.len();
+                for (age, (mut version, ipa)) in group.into_iter().enumerate() {
+                    if age > 0 {
+                        version.version = format!("{}-{}", version.version, count - age);
+                        tracing::info!(
+                            "App {} has several IPAs for version {}: publishing {} as version {}",
+                            app_name,
+                            label,
+                            ipa.filename,
+                            version.version
+                        );
+                    }
+                    resolved.push(version);
+                }
+            }
+            DuplicatePolicy::Fail => {
+                tracing::error!(
+                    "App {} has several IPAs for version {}: {}",
+                    app_name,
+                    label,
+                    filenames
+                );
+                anyhow::bail!(
+                    "App {} has several IPAs for version {} ({}); remove all but one or change duplicateVersions",
+                    app_name,
+                    label,
+                    filenames
+                );
+            }
+        }
+    }
+
+    Ok(resolved)
 }
 
 /// Merge manual versions (from config.json) with discovered versions (from IPA files)
-/// Manual versions take precedence - if a version string exists in both, use the manual entry
-/// but update downloadURL and size from the discovered version if the IPA file exists
+/// Versions are keyed on (version, buildVersion), so several builds of the same marketing
+/// version can coexist. A manual entry without buildVersion matches the first discovered
+/// build of its version.
+/// Manual versions take precedence - if a version exists in both, use the manual entry
+/// but take the IPA and size from the discovered version
+/// The result is sorted newest first according to `order`
 fn merge_versions(
     manual_versions: Vec<AppVersion>,
     discovered_versions: Vec<AppVersion>,
-    auth_token: Option<&str>,
+    order: VersionOrder,
 ) -> Vec<AppVersion> {
-    use std::collections::HashMap;
-
-    // Index manual versions by version string
-    let mut manual_map: HashMap<String, AppVersion> = manual_versions
+    // Index manual versions by (version, build)
+    let mut manual_map: HashMap<VersionKey, AppVersion> = manual_versions
         .into_iter()
-        .map(|v| (v.version.clone(), v))
+        .map(|v| (version_key(&v), v))
         .collect();
 
     // Track which manual versions were matched with discovered IPAs
     let mut matched_manual_versions = std::collections::HashSet::new();
 
+    // Discovered versions without a manual entry; kept apart so the wildcard keys below
+    // only ever match entries written in config.json or app.json
+    let mut unmatched_discovered = Vec::new();
+
     // Process discovered versions
     for discovered in discovered_versions {
-        if let Some(manual) = manual_map.get_mut(&discovered.version) {
+        let exact_key = version_key(&discovered);
+        let (version, build, tweak) = exact_key.clone();
+
+        // Prefer a manual entry for this exact build, then ones that leave the build or
+        // the tweak version open; those only apply to the first matching IPA
+        let wildcard_keys = [
+            (version.clone(), build, None),
+            (version.clone(), None, tweak),
+            (version, None, None),
+        ];
+        let matched_key = if manual_map.contains_key(&exact_key) {
+            Some(exact_key.clone())
+        } else {
+            wildcard_keys
+                .into_iter()
+                .find(|key| manual_map.contains_key(key) && !matched_manual_versions.contains(key))
+        };
+
+        if let Some((key, manual)) =
+            matched_key.and_then(|key| manual_map.get_mut(&key).map(|manual| (key, manual)))
+        {
             // Version exists in both manual and discovered
-            // Keep manual entry but update download URL and size from IPA file
-            manual.download_url = discovered.download_url;
+            // Keep manual entry but download the IPA file instead
+            manual.ipa = discovered.ipa;
             manual.size = discovered.size;
-            matched_manual_versions.insert(discovered.version.clone());
+            if manual.build_version.is_none() {
+                manual.build_version = discovered.build_version;
+            }
+            // OS requirements from config.json override the ones read from the IPA
+            if manual.min_os_version.is_none() {
+                manual.min_os_version = discovered.min_os_version;
+            }
+            if manual.max_os_version.is_none() {
+                manual.max_os_version = discovered.max_os_version;
+            }
+            if manual.injected_libraries.is_empty() {
+                manual.injected_libraries = discovered.injected_libraries;
+            }
+            if manual.tweak_version.is_none() {
+                manual.tweak_version = discovered.tweak_version;
+            }
             tracing::debug!(
                 "Merged version {}: kept manual metadata, updated URL and size from IPA",
                 discovered.version
             );
+            matched_manual_versions.insert(key);
         } else {
             // This is a new discovered version not in manual config
-            manual_map.insert(discovered.version.clone(), discovered);
-            tracing::debug!("Added discovered version {}", manual_map.len());
-        }
-    }
-
-    // For manual versions that don't have matching IPAs, append auth token if present
-    // Skip obfuscated URLs (those starting with /download/) as they use the token for auth
-    if let Some(token) = auth_token {
-        for (version_str, version) in manual_map.iter_mut() {
-            if !matched_manual_versions.contains(version_str) {
-                // This manual version doesn't have a matching IPA
-                // Only append token to non-obfuscated URLs
-                if !version.download_url.contains('?')
-                    && !version.download_url.contains("/download/")
-                {
-                    version.download_url.push_str("?token=");
-                    version.download_url.push_str(token);
-                }
-            }
+            tracing::debug!(
+                "Added discovered version {} (build {})",
+                discovered.version,
+                discovered.build_version.as_deref().unwrap_or("unknown")
+            );
+            unmatched_discovered.push(discovered);
         }
     }
 
-    // Convert back to vector and sort by date (newest first)
+    // Convert back to vector, newest first
     let mut merged: Vec<AppVersion> = manual_map.into_values().collect();
-    merged.sort_by(|a, b| b.date.cmp(&a.date));
+    merged.extend(unmatched_discovered);
+    merged.sort_by(|a, b| compare_release(order, b, a));
 
     merged
 }
 
+/// Orders two versions of an app from oldest to newest
+fn compare_release(order: VersionOrder, a: &AppVersion, b: &AppVersion) -> Ordering {
+    let optional = |a: &Option<String>, b: &Option<String>| match (a, b) {
+        (Some(a), Some(b)) => compare_versions(a, b),
+        _ => a.is_some().cmp(&b.is_some()),
+    };
+    let semantic = compare_versions(&a.version, &b.version)
+        .then_with(|| optional(&a.build_version, &b.build_version))
+        .then_with(|| optional(&a.tweak_version, &b.tweak_version));
+
+    match order {
+        VersionOrder::Semantic => semantic.then_with(|| a.date.cmp(&b.date)),
+        VersionOrder::Date => a.date.cmp(&b.date).then(semantic),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::discovery::IpaEntry;
+    use crate::sidecar::Sidecar;
+    use std::path::PathBuf;
+
+    /// Builds and renders a repository the way the repository.json route does
+    fn generate_repository(
+        config: Config,
+        ipa_index: &IpaIndex,
+        base_url: &str,
+        signing: Option<&UrlSigning>,
+        auth_token: Option<&str>,
+        publish_encrypted: bool,
+    ) -> Result<Repository> {
+        let catalog = build_catalog(config, ipa_index, publish_encrypted)?;
+        Ok(render_repository(
+            &catalog,
+            &Digests::new(),
+            base_url,
+            signing,
+            auth_token,
+        ))
+    }
+
+    fn config(apps: serde_json::Value) -> Config {
+        serde_json::from_value(serde_json::json!({
+            "name": "Repo",
+            "identifier": "com.example.repo",
+            "website": "https://example.com",
+            "tintColor": "ffffff",
+            "iconURL": "https://example.com/icon.png",
+            "sourceURL": "https://example.com",
+            "apps": apps
+        }))
+        .unwrap()
+    }
+
+    fn app(name: &str, bundle_identifier: &str) -> serde_json::Value {
+        serde_json::json!({
+            "name": name,
+            "bundleIdentifier": bundle_identifier,
+            "developerName": "Dev",
+            "localizedDescription": "",
+            "iconURL": "",
+            "tintColor": "ffffff",
+            "category": "utilities",
+            "screenshotURLs": [],
+            "appPermissions": { "entitlements": [], "privacy": {} }
+        })
+    }
+
+    fn ipa(app_name: &str, filename: &str) -> IpaEntry {
+        IpaEntry {
+            app_name: app_name.to_string(),
+            filename: filename.to_string(),
+            path: PathBuf::from(format!("/apps/{}/{}", app_name, filename)),
+            size: 1000,
+            release_date: "2025-01-13T00:00:00Z".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_reconcile_entitlements() {
+        let signed = vec!["aps-environment".to_string(), "get-task-allow".to_string()];
+
+        let mut empty = Vec::new();
+        reconcile_entitlements("App", &mut empty, &signed);
+        assert_eq!(empty, signed);
+
+        let mut stale = vec![
+            "aps-environment".to_string(),
+            "com.apple.developer.icloud-services".to_string(),
+        ];
+        reconcile_entitlements("App", &mut stale, &signed);
+        assert_eq!(stale, signed);
+    }
+
+    #[test]
+    fn test_merge_privacy() {
+        let extracted = HashMap::from([
+            (
+                "NSCameraUsageDescription".to_string(),
+                "From IPA".to_string(),
+            ),
+            (
+                "NSMicrophoneUsageDescription".to_string(),
+                "Record audio".to_string(),
+            ),
+        ]);
+        let mut configured = HashMap::from([
+            (
+                "NSCameraUsageDescription".to_string(),
+                "From config".to_string(),
+            ),
+            (
+                "NSContactsUsageDescription".to_string(),
+                "Stale".to_string(),
+            ),
+        ]);
+
+        merge_privacy("App", &mut configured, &extracted);
+
+        assert_eq!(configured.len(), 3);
+        assert_eq!(configured["NSCameraUsageDescription"], "From config");
+        assert_eq!(configured["NSMicrophoneUsageDescription"], "Record audio");
+        assert_eq!(configured["NSContactsUsageDescription"], "Stale");
+    }
+
+    #[test]
+    fn test_generate_repository_fills_icon_url() {
+        let config = config(serde_json::json!([
+            app("WithIcon", "com.example.with"),
+            app("WithoutIcon", "com.example.without")
+        ]));
+
+        let mut with_icon = ipa("WithIcon", "WithIcon_1.0.ipa");
+        with_icon.icon_path = Some("Payload/App.app/AppIcon60x60@3x.png".to_string());
+
+        let mut index = IpaIndex::new();
+        index.insert("WithIcon".to_string(), vec![with_icon]);
+        index.insert(
+            "WithoutIcon".to_string(),
+            vec![ipa("WithoutIcon", "WithoutIcon_1.0.ipa")],
+        );
+
+        let repo = generate_repository(
+            config,
+            &index,
+            "https://repo.example",
+            None,
+            Some("t"),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(
+            repo.apps[0].icon_url,
+            "https://repo.example/icons/WithIcon.png?token=t"
+        );
+        assert_eq!(repo.apps[1].icon_url, "");
+    }
+
+    #[test]
+    fn test_render_repository_mints_download_urls() {
+        let mut app = app("App", "com.example.app");
+        app["versions"] = serde_json::json!([
+            {
+                "version": "0.9", "date": "2024-01-01", "localizedDescription": "",
+                "downloadURL": "https://mirror.example/App_0.9.ipa", "size": 1
+            },
+            {
+                "version": "0.8", "date": "2023-01-01", "localizedDescription": "",
+                "downloadURL": "https://repo.example/download/abc", "size": 1
+            }
+        ]);
+        let mut index = IpaIndex::new();
+        index.insert("App".to_string(), vec![ipa("App", "App_1.0.ipa")]);
+
+        let catalog = build_catalog(config(serde_json::json!([app])), &index, false).unwrap();
+        // The catalog doesn't depend on the request
+        assert_eq!(catalog.apps[0].versions[0].download_url, "");
+
+        let key = (PathBuf::from("/apps/App/App_1.0.ipa"), 0);
+        let digests = Digests::from([(key, "abc".to_string())]);
+        let repo = render_repository(&catalog, &digests, "https://repo.example/", None, Some("t"));
+        assert_eq!(repo.apps[0].versions[0].sha256.as_deref(), Some("abc"));
+        let urls: Vec<&str> = repo.apps[0]
+            .versions
+            .iter()
+            .map(|v| v.download_url.as_str())
+            .collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://repo.example/apps/App/App_1.0.ipa?token=t",
+                "https://mirror.example/App_0.9.ipa?token=t",
+                "https://repo.example/download/abc"
+            ]
+        );
+        assert_eq!(repo.source_url, "https://repo.example");
+    }
+
+    #[tokio::test]
+    async fn test_catalog_cache_follows_snapshots() {
+        let merged = Arc::new(MergedConfig {
+            config: config(serde_json::json!([app("App", "com.example.app")])),
+            app_file_errors: Vec::new(),
+        });
+        let mut ipas = IpaIndex::new();
+        ipas.insert("App".to_string(), vec![ipa("App", "App_1.0.ipa")]);
+        let index = Arc::new(IndexSnapshot::new(ipas, Arc::default(), None));
+        let cache = CatalogCache::default();
+
+        let catalog = cache
+            .get(merged.clone(), index.clone(), false)
+            .await
+            .unwrap();
+        assert_eq!(catalog.apps[0].versions.len(), 1);
+        assert!(Arc::ptr_eq(
+            &catalog,
+            &cache.get(merged.clone(), index, false).await.unwrap()
+        ));
+
+        // A new index snapshot is matched again
+        let empty = Arc::new(IndexSnapshot::new(IpaIndex::new(), Arc::default(), None));
+        let catalog = cache.get(merged, empty, false).await.unwrap();
+        assert!(catalog.apps[0].versions.is_empty());
+    }
+
+    #[test]
+    fn test_generate_repository_skips_encrypted_ipas() {
+        let mut encrypted = ipa("App", "App_2.0.ipa");
+        encrypted.encrypted = true;
+
+        let mut index = IpaIndex::new();
+        index.insert(
+            "App".to_string(),
+            vec![ipa("App", "App_1.0.ipa"), encrypted],
+        );
+
+        let config = || config(serde_json::json!([app("App", "com.example.app")]));
+
+        let repo = generate_repository(config(), &index, "https://repo.example", None, None, false)
+            .unwrap();
+        let versions: Vec<&str> = repo.apps[0]
+            .versions
+            .iter()
+            .map(|v| v.version.as_str())
+            .collect();
+        assert_eq!(versions, vec!["1.0"]);
+
+        let repo = generate_repository(config(), &index, "https://repo.example", None, None, true)
+            .unwrap();
+        assert_eq!(repo.apps[0].versions.len(), 2);
+    }
+
+    #[test]
+    fn test_describe_injected_libraries() {
+        assert_eq!(
+            describe_injected_libraries("Version 1.0".to_string(), &[]),
+            "Version 1.0"
+        );
+
+        let libraries = vec![
+            InjectedLibrary {
+                name: "Orion.framework".to_string(),
+                version: None,
+            },
+            InjectedLibrary {
+                name: "YTLite.dylib".to_string(),
+                version: Some("5.2.1".to_string()),
+            },
+        ];
+        assert_eq!(
+            describe_injected_libraries("Version 1.0".to_string(), &libraries),
+            "Version 1.0\n\nIncludes: Orion.framework, YTLite.dylib 5.2.1"
+        );
+    }
 
     #[test]
     fn test_parse_version_from_filename_three_parts() {
         let result =
-            parse_version_from_filename("YourApp_5.2b1_20.26.7.ipa", "2025-01-13").unwrap();
+            parse_version_from_filename("YourApp_5.2b1_20.26.7.ipa", "2025-01-13", &[]).unwrap();
         assert_eq!(result.version, "20.26.7");
         assert!(result.description.contains("5.2b1"));
         assert_eq!(result.date, "2025-01-13");
@@ -261,17 +1139,48 @@ mod tests {
 
     #[test]
     fn test_parse_version_from_filename_two_parts() {
-        let result = parse_version_from_filename("MyApp_1.2.3.ipa", "2025-01-13").unwrap();
+        let result = parse_version_from_filename("MyApp_1.2.3.ipa", "2025-01-13", &[]).unwrap();
         assert_eq!(result.version, "1.2.3");
         assert_eq!(result.date, "2025-01-13");
     }
 
     #[test]
     fn test_parse_version_from_filename_invalid() {
-        let result = parse_version_from_filename("invalid.txt", "2025-01-13");
+        let result = parse_version_from_filename("invalid.txt", "2025-01-13", &[]);
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_parse_version_from_filename_with_patterns() {
+        let nightly: FilenamePattern =
+            r"(?P<app>.+)-(?P<version>[\d.]+)-(?P<channel>nightly)\.(?P<build>\d+)\.ipa"
+                .parse()
+                .unwrap();
+        let tweaked: FilenamePattern = r"(?P<app>.+)-v(?P<version>[\d.]+)\+(?P<tweak>.+)\.ipa"
+            .parse()
+            .unwrap();
+        let patterns = [&nightly, &tweaked];
+
+        let result =
+            parse_version_from_filename("YouTube-v20.26.7+5.2b1.ipa", "2025-01-13", &patterns)
+                .unwrap();
+        assert_eq!(result.version, "20.26.7");
+        assert_eq!(result.tweak.as_deref(), Some("5.2b1"));
+        assert_eq!(result.description, "Version 20.26.7 (tweak version: 5.2b1)");
+
+        let result =
+            parse_version_from_filename("App-1.4-nightly.812.ipa", "2025-01-13", &patterns)
+                .unwrap();
+        assert_eq!(result.version, "1.4");
+        assert_eq!(result.build.as_deref(), Some("812"));
+        assert_eq!(result.channel.as_deref(), Some("nightly"));
+
+        // No pattern matches: the built-in shapes still apply
+        let result =
+            parse_version_from_filename("MyApp_1.2.3.ipa", "2025-01-13", &patterns).unwrap();
+        assert_eq!(result.version, "1.2.3");
+    }
+
     #[test]
     fn test_merge_versions_keeps_manual_metadata() {
         let manual = vec![AppVersion {
@@ -280,25 +1189,31 @@ mod tests {
             localized_description: "Custom description".to_string(),
             download_url: "https://old-url.com/file.ipa".to_string(),
             size: 1000,
+            ..Default::default()
         }];
 
         let discovered = vec![AppVersion {
             version: "1.0.0".to_string(),
             date: "2025-01-13".to_string(),
             localized_description: "Auto-generated description".to_string(),
-            download_url: "https://new-url.com/file.ipa".to_string(),
+            ipa: Some(IpaSource {
+                app_name: "App".to_string(),
+                filename: "App_1.0.0.ipa".to_string(),
+                key: (PathBuf::from("/apps/App/App_1.0.0.ipa"), 0),
+            }),
             size: 2000,
+            ..Default::default()
         }];
 
-        let merged = merge_versions(manual, discovered, None);
+        let merged = merge_versions(manual, discovered, VersionOrder::Semantic);
 
         assert_eq!(merged.len(), 1);
         assert_eq!(merged[0].version, "1.0.0");
         // Manual metadata preserved
         assert_eq!(merged[0].date, "2025-01-01");
         assert_eq!(merged[0].localized_description, "Custom description");
-        // Discovered URL and size updated
-        assert_eq!(merged[0].download_url, "https://new-url.com/file.ipa");
+        // Discovered IPA and size taken over
+        assert_eq!(merged[0].ipa.as_ref().unwrap().filename, "App_1.0.0.ipa");
         assert_eq!(merged[0].size, 2000);
     }
 
@@ -310,6 +1225,7 @@ mod tests {
             localized_description: "Version 1".to_string(),
             download_url: "https://example.com/v1.ipa".to_string(),
             size: 1000,
+            ..Default::default()
         }];
 
         let discovered = vec![
@@ -319,6 +1235,7 @@ mod tests {
                 localized_description: "Auto v1".to_string(),
                 download_url: "https://example.com/v1-new.ipa".to_string(),
                 size: 1500,
+                ..Default::default()
             },
             AppVersion {
                 version: "2.0.0".to_string(),
@@ -326,14 +1243,14 @@ mod tests {
                 localized_description: "Auto v2".to_string(),
                 download_url: "https://example.com/v2.ipa".to_string(),
                 size: 2000,
+                ..Default::default()
             },
         ];
 
-        let merged = merge_versions(manual, discovered, None);
+        let merged = merge_versions(manual, discovered, VersionOrder::Semantic);
 
         assert_eq!(merged.len(), 2);
-        // Should be sorted by date (newest first)
-        // v2 is newer (2025-01-13) than v1 (2025-01-01)
+        // Sorted by version number (newest first), whatever the dates
         assert_eq!(merged[0].version, "2.0.0");
         assert_eq!(merged[1].version, "1.0.0");
     }
@@ -347,12 +1264,442 @@ mod tests {
             localized_description: "Auto-generated".to_string(),
             download_url: "https://example.com/file.ipa".to_string(),
             size: 1000,
+            ..Default::default()
         }];
 
-        let merged = merge_versions(manual, discovered, None);
+        let merged = merge_versions(manual, discovered, VersionOrder::Semantic);
 
         assert_eq!(merged.len(), 1);
         assert_eq!(merged[0].version, "1.0.0");
         assert_eq!(merged[0].localized_description, "Auto-generated");
     }
+
+    #[test]
+    fn test_merge_versions_os_requirements() {
+        let manual = vec![
+            AppVersion {
+                version: "1.0.0".to_string(),
+                date: "2025-01-01".to_string(),
+                max_os_version: Some("17.0".to_string()),
+                ..Default::default()
+            },
+            AppVersion {
+                version: "2.0.0".to_string(),
+                date: "2025-01-02".to_string(),
+                min_os_version: Some("16.0".to_string()),
+                ..Default::default()
+            },
+        ];
+
+        let discovered = vec![
+            AppVersion {
+                version: "1.0.0".to_string(),
+                min_os_version: Some("14.0".to_string()),
+                ..Default::default()
+            },
+            AppVersion {
+                version: "2.0.0".to_string(),
+                min_os_version: Some("15.0".to_string()),
+                ..Default::default()
+            },
+        ];
+
+        let merged = merge_versions(manual, discovered, VersionOrder::Semantic);
+
+        // Extracted minimum fills in when config.json has none
+        assert_eq!(merged[1].version, "1.0.0");
+        assert_eq!(merged[1].min_os_version.as_deref(), Some("14.0"));
+        assert_eq!(merged[1].max_os_version.as_deref(), Some("17.0"));
+        // Manual minimum wins over the extracted one
+        assert_eq!(merged[0].version, "2.0.0");
+        assert_eq!(merged[0].min_os_version.as_deref(), Some("16.0"));
+    }
+
+    #[test]
+    fn test_merge_versions_keeps_distinct_builds() {
+        let discovered = vec![
+            AppVersion {
+                version: "1.0.0".to_string(),
+                build_version: Some("100".to_string()),
+                date: "2025-01-01".to_string(),
+                ..Default::default()
+            },
+            AppVersion {
+                version: "1.0.0".to_string(),
+                build_version: Some("101".to_string()),
+                date: "2025-01-02".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let merged = merge_versions(vec![], discovered, VersionOrder::Semantic);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].build_version.as_deref(), Some("101"));
+        assert_eq!(merged[1].build_version.as_deref(), Some("100"));
+    }
+
+    #[test]
+    fn test_generate_repository_keeps_tweak_version() {
+        let mut older = ipa("YTLite", "YouTube_5.2b1_20.26.7.ipa");
+        let mut newer = ipa("YTLite", "YouTube_5.2b2_20.26.7.ipa");
+        for entry in [&mut older, &mut newer] {
+            entry.bundle_version = Some("20.26.7".to_string());
+            entry.bundle_short_version = Some("20.26.7".to_string());
+        }
+
+        let mut index = IpaIndex::new();
+        index.insert("YTLite".to_string(), vec![older, newer]);
+
+        let repo = generate_repository(
+            config(serde_json::json!([app("YTLite", "com.google.ios.youtube")])),
+            &index,
+            "https://repo.example",
+            None,
+            None,
+            false,
+        )
+        .unwrap();
+
+        let mut versions: Vec<(Option<&str>, &str)> = repo.apps[0]
+            .versions
+            .iter()
+            .map(|v| (v.tweak_version.as_deref(), v.localized_description.as_str()))
+            .collect();
+        versions.sort();
+        assert_eq!(
+            versions,
+            vec![
+                (Some("5.2b1"), "Version 20.26.7 (tweak version: 5.2b1)"),
+                (Some("5.2b2"), "Version 20.26.7 (tweak version: 5.2b2)")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generate_repository_keeps_plain_and_tweaked_builds() {
+        let mut plain = ipa("YouTube", "YouTube_20.26.7.ipa");
+        let mut tweaked = ipa("YouTube", "YouTube_5.2b1_20.26.7.ipa");
+        for entry in [&mut plain, &mut tweaked] {
+            entry.bundle_version = Some("20.26.7".to_string());
+            entry.bundle_short_version = Some("20.26.7".to_string());
+        }
+
+        let mut index = IpaIndex::new();
+        index.insert("YouTube".to_string(), vec![plain, tweaked]);
+
+        let repo = generate_repository(
+            config(serde_json::json!([app(
+                "YouTube",
+                "com.google.ios.youtube"
+            )])),
+            &index,
+            "https://repo.example",
+            None,
+            None,
+            false,
+        )
+        .unwrap();
+
+        // Discovered versions never act as manual entries for each other
+        let mut tweaks: Vec<Option<&str>> = repo.apps[0]
+            .versions
+            .iter()
+            .map(|v| v.tweak_version.as_deref())
+            .collect();
+        tweaks.sort();
+        assert_eq!(tweaks, vec![None, Some("5.2b1")]);
+    }
+
+    #[test]
+    fn test_generate_repository_ignores_underscores_of_plist_ipas() {
+        let mut underscored = ipa("MyApp", "My_App_1.0.ipa");
+        let mut suffixed = ipa("MyApp", "App_1.0_fixed.ipa");
+        for (entry, build) in [(&mut underscored, "1"), (&mut suffixed, "2")] {
+            entry.bundle_version = Some(build.to_string());
+            entry.bundle_short_version = Some("1.0".to_string());
+        }
+
+        let mut index = IpaIndex::new();
+        index.insert("MyApp".to_string(), vec![underscored, suffixed]);
+
+        let repo = generate_repository(
+            config(serde_json::json!([app("MyApp", "com.example.myapp")])),
+            &index,
+            "https://repo.example",
+            None,
+            None,
+            false,
+        )
+        .unwrap();
+
+        // Neither filename follows AppName_[tweak_]version.ipa for version 1.0
+        let versions = &repo.apps[0].versions;
+        assert_eq!(versions.len(), 2);
+        for version in versions {
+            assert_eq!(version.tweak_version, None);
+            assert_eq!(version.localized_description, "Version 1.0");
+        }
+    }
+
+    #[test]
+    fn test_generate_repository_auto_publishes_unmatched_ipas() {
+        let mut known = ipa("Known", "Known_1.0.ipa");
+        known.bundle_identifier = Some("com.example.known".to_string());
+        let mut new = ipa("NewApp", "NewApp_2.0.ipa");
+        new.bundle_identifier = Some("com.example.new".to_string());
+        new.bundle_name = Some("New App".to_string());
+        new.icon_path = Some("Payload/New.app/AppIcon60x60@3x.png".to_string());
+
+        let mut index = IpaIndex::new();
+        index.insert("Known".to_string(), vec![known]);
+        index.insert("NewApp".to_string(), vec![new]);
+
+        let generate = |auto_publish: serde_json::Value| {
+            let mut config = config(serde_json::json!([app("Known", "com.example.known")]));
+            config.auto_publish = serde_json::from_value(auto_publish).unwrap();
+            generate_repository(config, &index, "https://repo.example", None, None, false).unwrap()
+        };
+
+        // Disabled by default: the unmatched directory is only logged
+        assert_eq!(generate(serde_json::Value::Null).apps.len(), 1);
+
+        let repo = generate(serde_json::json!({ "category": "entertainment" }));
+        assert_eq!(repo.apps.len(), 2);
+
+        let synthesized = &repo.apps[1];
+        assert_eq!(synthesized.name, "New App");
+        assert_eq!(synthesized.bundle_identifier, "com.example.new");
+        assert_eq!(synthesized.category, "entertainment");
+        assert_eq!(synthesized.tint_color, "ffffff");
+        assert_eq!(
+            synthesized.icon_url,
+            "https://repo.example/icons/NewApp.png"
+        );
+        assert_eq!(synthesized.versions.len(), 1);
+        assert_eq!(synthesized.versions[0].version, "2.0");
+    }
+
+    #[test]
+    fn test_generate_repository_auto_publishes_unique_names() {
+        // Both bundle names and one directory name clash with a configured app
+        let mut first = ipa("Known", "First_1.0.ipa");
+        first.bundle_identifier = Some("com.example.first".to_string());
+        first.bundle_name = Some("Known".to_string());
+        let mut second = ipa("Other", "Second_1.0.ipa");
+        second.bundle_identifier = Some("com.example.second".to_string());
+        second.bundle_name = Some("Known".to_string());
+        let mut known = ipa("Elsewhere", "Known_1.0.ipa");
+        known.bundle_identifier = Some("com.example.known".to_string());
+
+        let mut index = IpaIndex::new();
+        index.insert("Known".to_string(), vec![first]);
+        index.insert("Other".to_string(), vec![second]);
+        index.insert("Elsewhere".to_string(), vec![known]);
+
+        // Pinned elsewhere, so the Known directory is free to be auto-published
+        let mut known_app = app("Known", "com.example.known");
+        known_app["directory"] = "Elsewhere".into();
+        let mut config = config(serde_json::json!([known_app]));
+        config.auto_publish = serde_json::from_value(serde_json::json!({})).unwrap();
+        let repo =
+            generate_repository(config, &index, "https://repo.example", None, None, false).unwrap();
+
+        let names: Vec<&str> = repo.apps.iter().map(|app| app.name.as_str()).collect();
+        assert_eq!(names, vec!["Known", "Known (2)", "Other"]);
+    }
+
+    #[test]
+    fn test_generate_repository_bundle_id_mismatch_policy() {
+        let mut own = ipa("App", "App_1.0.ipa");
+        own.bundle_identifier = Some("com.example.app".to_string());
+        let mut stray = ipa("App", "Stray_2.0.ipa");
+        stray.bundle_identifier = Some("com.example.stray".to_string());
+
+        let mut index = IpaIndex::new();
+        index.insert("App".to_string(), vec![own, stray]);
+
+        let published = |policy: &str| {
+            let mut config = config(serde_json::json!([app("App", "com.example.app")]));
+            config.bundle_id_mismatch = serde_json::from_value(serde_json::json!(policy)).unwrap();
+            generate_repository(config, &index, "https://repo.example", None, None, false)
+                .unwrap()
+                .apps[0]
+                .versions
+                .len()
+        };
+
+        assert_eq!(published("warn"), 2);
+        assert_eq!(published("publish"), 2);
+        assert_eq!(published("exclude"), 1);
+    }
+
+    #[test]
+    fn test_resolve_duplicates() {
+        let mut old = ipa("App", "App_1.0.ipa");
+        old.mtime_secs = 100;
+        let mut reupload = ipa("App", "App_1.0_fixed.ipa");
+        reupload.mtime_secs = 200;
+        let other = ipa("App", "App_2.0.ipa");
+
+        let version = |version: &str, url: &str| AppVersion {
+            version: version.to_string(),
+            download_url: url.to_string(),
+            ..Default::default()
+        };
+        let discovered = || {
+            vec![
+                (version("1.0", "old"), &old),
+                (version("2.0", "other"), &other),
+                (version("1.0", "reupload"), &reupload),
+            ]
+        };
+        let published = |versions: Vec<AppVersion>| {
+            versions
+                .into_iter()
+                .map(|v| (v.version, v.download_url))
+                .collect::<Vec<_>>()
+        };
+
+        let newest = resolve_duplicates("App", DuplicatePolicy::Newest, discovered()).unwrap();
+        assert_eq!(
+            published(newest),
+            vec![
+                ("1.0".to_string(), "reupload".to_string()),
+                ("2.0".to_string(), "other".to_string())
+            ]
+        );
+
+        let both = resolve_duplicates("App", DuplicatePolicy::Disambiguate, discovered()).unwrap();
+        assert_eq!(
+            published(both),
+            vec![
+                ("1.0".to_string(), "reupload".to_string()),
+                ("1.0-1".to_string(), "old".to_string()),
+                ("2.0".to_string(), "other".to_string())
+            ]
+        );
+
+        let err = resolve_duplicates("App", DuplicatePolicy::Fail, discovered()).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("version 1.0 (App_1.0_fixed.ipa, App_1.0.ipa)"));
+    }
+
+    #[test]
+    fn test_merge_versions_orders_versions() {
+        let version = |version: &str, build: &str, date: &str| AppVersion {
+            version: version.to_string(),
+            build_version: Some(build.to_string()),
+            date: date.to_string(),
+            ..Default::default()
+        };
+        let discovered = || {
+            vec![
+                version("1.9", "90", "2025-01-13"),
+                version("1.10b1", "100", "2025-01-10"),
+                version("1.10", "101", "2025-01-10"),
+                version("1.10", "102", "2025-01-10"),
+            ]
+        };
+        let order = |versions: Vec<AppVersion>| {
+            versions
+                .into_iter()
+                .map(|v| v.build_version.unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        // A touched old IPA doesn't become the latest release
+        let semantic = merge_versions(vec![], discovered(), VersionOrder::Semantic);
+        assert_eq!(order(semantic), vec!["102", "101", "100", "90"]);
+
+        let by_date = merge_versions(vec![], discovered(), VersionOrder::Date);
+        assert_eq!(order(by_date), vec!["90", "102", "101", "100"]);
+    }
+
+    #[test]
+    fn test_generate_repository_applies_sidecar() {
+        let mut notes = ipa("App", "App_1.0.ipa");
+        notes.sidecar = Sidecar {
+            localized_description: Some("Fixed a crash".to_string()),
+            beta: true,
+            min_os_version: Some("15.0".to_string()),
+            ..Default::default()
+        };
+        let mut yanked = ipa("App", "App_2.0.ipa");
+        yanked.sidecar.hidden = true;
+
+        let mut index = IpaIndex::new();
+        index.insert("App".to_string(), vec![notes, yanked]);
+        let config = config(serde_json::json!([app("App", "com.example.app")]));
+
+        let repo =
+            generate_repository(config, &index, "https://repo.example", None, None, false).unwrap();
+        let versions = &repo.apps[0].versions;
+
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].version, "1.0");
+        assert_eq!(versions[0].localized_description, "Fixed a crash");
+        assert_eq!(versions[0].channel.as_deref(), Some("beta"));
+        assert_eq!(versions[0].min_os_version.as_deref(), Some("15.0"));
+    }
+
+    #[test]
+    fn test_merge_versions_matches_manual_by_build() {
+        let manual = vec![
+            AppVersion {
+                version: "1.0.0".to_string(),
+                build_version: Some("101".to_string()),
+                localized_description: "Hotfix build".to_string(),
+                ..Default::default()
+            },
+            AppVersion {
+                version: "2.0.0".to_string(),
+                localized_description: "Any build".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let discovered = vec![
+            AppVersion {
+                version: "1.0.0".to_string(),
+                build_version: Some("100".to_string()),
+                localized_description: "Auto 100".to_string(),
+                ..Default::default()
+            },
+            AppVersion {
+                version: "1.0.0".to_string(),
+                build_version: Some("101".to_string()),
+                localized_description: "Auto 101".to_string(),
+                ..Default::default()
+            },
+            AppVersion {
+                version: "2.0.0".to_string(),
+                build_version: Some("200".to_string()),
+                localized_description: "Auto 200".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let mut merged = merge_versions(manual, discovered, VersionOrder::Semantic);
+        merged.sort_by(|a, b| a.build_version.cmp(&b.build_version));
+
+        let descriptions: Vec<(&str, &str)> = merged
+            .iter()
+            .map(|v| {
+                (
+                    v.build_version.as_deref().unwrap(),
+                    v.localized_description.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            descriptions,
+            vec![
+                ("100", "Auto 100"),
+                ("101", "Hotfix build"),
+                ("200", "Any build")
+            ]
+        );
+    }
 }
@@ -0,0 +1,267 @@
+use anyhow::{Context, Result};
+use flate2::read::{DeflateDecoder, ZlibEncoder};
+use flate2::Compression;
+use std::io::Read;
+
+/// PNG file signature
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// A single PNG chunk
+struct Chunk<'a> {
+    kind: [u8; 4],
+    data: &'a [u8],
+}
+
+/// Converts an Apple CgBI-"crushed" PNG into a standard PNG
+/// Standard PNGs are returned unchanged.
+///
+/// Xcode optimizes bundled PNGs into a proprietary variant that browsers and
+/// AltStore can't display: an extra CgBI chunk, a raw deflate stream without
+/// zlib header, BGRA channel order and premultiplied alpha.
+pub fn normalize_png(data: &[u8]) -> Result<Vec<u8>> {
+    let chunks = parse_chunks(data)?;
+
+    if chunks.first().map(|c| &c.kind) != Some(b"CgBI") {
+        return Ok(data.to_vec());
+    }
+
+    let header = chunks
+        .iter()
+        .find(|c| &c.kind == b"IHDR")
+        .context("PNG has no IHDR chunk")?;
+    if header.data.len() < 13 {
+        anyhow::bail!("PNG IHDR chunk is truncated");
+    }
+
+    let width = u32::from_be_bytes(header.data[0..4].try_into()?) as usize;
+    let height = u32::from_be_bytes(header.data[4..8].try_into()?) as usize;
+    let bit_depth = header.data[8];
+    let color_type = header.data[9];
+    let interlaced = header.data[12] != 0;
+
+    // iOS only ever crushes 8-bit RGBA, non-interlaced images
+    if bit_depth != 8 || color_type != 6 || interlaced {
+        anyhow::bail!(
+            "Unsupported CgBI PNG (bit depth {}, color type {}, interlaced {})",
+            bit_depth,
+            color_type,
+            interlaced
+        );
+    }
+
+    // Concatenate IDAT chunks and inflate the raw deflate stream
+    let compressed: Vec<u8> = chunks
+        .iter()
+        .filter(|c| &c.kind == b"IDAT")
+        .flat_map(|c| c.data.iter().copied())
+        .collect();
+
+    let mut pixels = Vec::new();
+    DeflateDecoder::new(compressed.as_slice())
+        .read_to_end(&mut pixels)
+        .context("Failed to inflate CgBI image data")?;
+
+    let stride = width * 4;
+    if pixels.len() < height * (stride + 1) {
+        anyhow::bail!("CgBI image data is truncated");
+    }
+
+    unfilter(&mut pixels, stride, height)?;
+
+    // Swap BGRA -> RGBA and undo alpha premultiplication; rows are re-emitted unfiltered
+    for row in pixels.chunks_exact_mut(stride + 1).take(height) {
+        row[0] = 0;
+        for pixel in row[1..].chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+            let alpha = pixel[3] as u32;
+            if alpha > 0 && alpha < 255 {
+                for channel in &mut pixel[..3] {
+                    *channel = ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+                }
+            }
+        }
+    }
+    pixels.truncate(height * (stride + 1));
+
+    let mut idat = Vec::new();
+    ZlibEncoder::new(pixels.as_slice(), Compression::default())
+        .read_to_end(&mut idat)
+        .context("Failed to compress PNG image data")?;
+
+    // Rebuild the file, keeping ancillary chunks but dropping Apple-specific ones
+    let mut output = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut output, b"IHDR", header.data);
+    for chunk in &chunks {
+        match &chunk.kind {
+            b"CgBI" | b"IHDR" | b"IDAT" | b"IEND" | b"iDOT" => {}
+            kind => write_chunk(&mut output, kind, chunk.data),
+        }
+    }
+    write_chunk(&mut output, b"IDAT", &idat);
+    write_chunk(&mut output, b"IEND", &[]);
+
+    Ok(output)
+}
+
+/// Splits a PNG file into its chunks
+fn parse_chunks(data: &[u8]) -> Result<Vec<Chunk<'_>>> {
+    let mut rest = data.strip_prefix(PNG_SIGNATURE).context("Not a PNG file")?;
+    let mut chunks = Vec::new();
+
+    while rest.len() >= 12 {
+        let length = u32::from_be_bytes(rest[0..4].try_into()?) as usize;
+        let kind: [u8; 4] = rest[4..8].try_into()?;
+        let end = 8usize
+            .checked_add(length)
+            .and_then(|end| end.checked_add(4))
+            .filter(|end| *end <= rest.len())
+            .context("PNG chunk extends past the end of the file")?;
+
+        chunks.push(Chunk {
+            kind,
+            data: &rest[8..8 + length],
+        });
+
+        if &kind == b"IEND" {
+            break;
+        }
+        rest = &rest[end..];
+    }
+
+    Ok(chunks)
+}
+
+/// Appends a chunk with its length and CRC
+fn write_chunk(output: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    output.extend_from_slice(kind);
+    output.extend_from_slice(data);
+
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+    output.extend_from_slice(&crc.finalize().to_be_bytes());
+}
+
+/// Reverses PNG scanline filters in place (4 bytes per pixel)
+fn unfilter(pixels: &mut [u8], stride: usize, height: usize) -> Result<()> {
+    const BPP: usize = 4;
+    let row_len = stride + 1;
+
+    for y in 0..height {
+        let (previous, current) = pixels.split_at_mut(y * row_len);
+        let previous = if y == 0 {
+            None
+        } else {
+            Some(&previous[(y - 1) * row_len + 1..])
+        };
+        let filter = current[0];
+        let row = &mut current[1..row_len];
+
+        for x in 0..stride {
+            let left = if x >= BPP { row[x - BPP] } else { 0 };
+            let up = previous.map_or(0, |p| p[x]);
+            let up_left = match previous {
+                Some(p) if x >= BPP => p[x - BPP],
+                _ => 0,
+            };
+
+            let predictor = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                other => anyhow::bail!("Invalid PNG filter type {}", other),
+            };
+            row[x] = row[x].wrapping_add(predictor);
+        }
+    }
+
+    Ok(())
+}
+
+/// Paeth predictor from the PNG specification
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::DeflateEncoder;
+    use flate2::read::ZlibDecoder;
+
+    /// Builds a 2x1 CgBI PNG: one opaque blue pixel and one half-transparent red pixel
+    fn cgbi_png() -> Vec<u8> {
+        // Filter byte, then BGRA premultiplied: blue (255,0,0,255) and red at 50% alpha (0,0,128,128)
+        let raw = [0u8, 255, 0, 0, 255, 0, 0, 128, 128];
+
+        let mut compressed = Vec::new();
+        DeflateEncoder::new(&raw[..], Compression::default())
+            .read_to_end(&mut compressed)
+            .unwrap();
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&2u32.to_be_bytes());
+        header.extend_from_slice(&1u32.to_be_bytes());
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+        let mut png = PNG_SIGNATURE.to_vec();
+        write_chunk(&mut png, b"CgBI", &[0x50, 0x00, 0x20, 0x02]);
+        write_chunk(&mut png, b"IHDR", &header);
+        write_chunk(&mut png, b"IDAT", &compressed);
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    #[test]
+    fn test_normalize_cgbi_png() {
+        let normalized = normalize_png(&cgbi_png()).unwrap();
+        let chunks = parse_chunks(&normalized).unwrap();
+
+        let kinds: Vec<&[u8; 4]> = chunks.iter().map(|c| &c.kind).collect();
+        assert_eq!(kinds, vec![b"IHDR", b"IDAT", b"IEND"]);
+
+        let mut pixels = Vec::new();
+        ZlibDecoder::new(chunks[1].data)
+            .read_to_end(&mut pixels)
+            .unwrap();
+
+        // Filter byte, then RGBA with straight alpha
+        assert_eq!(pixels, vec![0, 0, 0, 255, 255, 255, 0, 0, 128]);
+    }
+
+    #[test]
+    fn test_standard_png_is_unchanged() {
+        let mut png = PNG_SIGNATURE.to_vec();
+        write_chunk(&mut png, b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]);
+        write_chunk(&mut png, b"IEND", &[]);
+
+        assert_eq!(normalize_png(&png).unwrap(), png);
+    }
+
+    #[test]
+    fn test_unfilter_sub_and_up() {
+        // Two rows of one pixel each: Sub on row 0 (no left neighbour), Up on row 1
+        let mut pixels = vec![1, 10, 20, 30, 40, 2, 1, 1, 1, 1];
+        unfilter(&mut pixels, 4, 2).unwrap();
+        assert_eq!(pixels, vec![1, 10, 20, 30, 40, 2, 11, 21, 31, 41]);
+    }
+
+    #[test]
+    fn test_not_a_png() {
+        assert!(normalize_png(b"GIF89a").is_err());
+    }
+}
@@ -0,0 +1,518 @@
+use crate::cache::{CacheKey, IconCache, IpaCache};
+use crate::discovery::{
+    discover_app_files, discover_ipas, is_app_directory_name, load_app_file, scan_app_directory,
+    AppFiles, IpaEntry, IpaIndex,
+};
+use crate::ipa_info::sha256_file;
+use crate::token::generate_download_token;
+use anyhow::{Context, Result};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Notify, RwLock, RwLockReadGuard};
+
+/// Quiet period to wait for after a filesystem event before re-indexing
+/// Copying a large IPA produces a burst of events; this coalesces them into one update
+const DEBOUNCE: Duration = Duration::from_millis(750);
+
+/// SHA-256 digests of indexed IPAs, keyed by path and modification time
+pub type Digests = HashMap<CacheKey, String>;
+
+/// Immutable view of the index, together with lookup tables derived from it
+/// Derived tables are rebuilt with every snapshot, so they can never go stale
+pub struct IndexSnapshot {
+    /// Discovered IPAs, keyed by app directory name
+    pub ipas: IpaIndex,
+    /// app.json files of the app directories, keyed by directory name
+    /// Shared between snapshots while unchanged, so derived data can be cached by identity
+    pub app_files: Arc<AppFiles>,
+    /// Signed-download file token -> (app directory name, position in `ipas`)
+    tokens: HashMap<String, (String, usize)>,
+}
+
+impl IndexSnapshot {
+    /// Builds a snapshot, precomputing download tokens if a secret is configured
+    pub fn new(ipas: IpaIndex, app_files: Arc<AppFiles>, download_secret: Option<&str>) -> Self {
+        let mut tokens = HashMap::new();
+
+        if let Some(secret) = download_secret {
+            for (app_name, entries) in &ipas {
+                for (position, entry) in entries.iter().enumerate() {
+                    let token = generate_download_token(app_name, &entry.filename, Some(secret));
+                    tokens.insert(token, (app_name.clone(), position));
+                }
+            }
+        }
+
+        Self {
+            ipas,
+            app_files,
+            tokens,
+        }
+    }
+
+    /// Looks up the IPA a signed-download file token refers to
+    pub fn find_by_token(&self, token: &str) -> Option<&IpaEntry> {
+        let (app_name, position) = self.tokens.get(token)?;
+        self.ipas.get(app_name)?.get(*position)
+    }
+}
+
+/// Thread-safe handle to the current IPA index snapshot
+/// Readers get a cheap `Arc` clone; the indexer swaps in a whole new snapshot on change
+#[derive(Clone)]
+pub struct IndexHandle {
+    inner: Arc<RwLock<Arc<IndexSnapshot>>>,
+    download_secret: Option<Arc<String>>,
+    /// Filled in by the background hasher; kept out of the snapshots so that publishing
+    /// a digest doesn't rebuild the index
+    digests: Arc<RwLock<Digests>>,
+    /// Wakes the hasher when new IPAs may need hashing
+    changed: Arc<Notify>,
+}
+
+impl IndexHandle {
+    /// Creates a handle holding the given initial index
+    pub fn new(index: IpaIndex, app_files: AppFiles, download_secret: Option<Arc<String>>) -> Self {
+        let snapshot = IndexSnapshot::new(
+            index,
+            Arc::new(app_files),
+            download_secret.as_deref().map(|s| s.as_str()),
+        );
+        Self {
+            inner: Arc::new(RwLock::new(Arc::new(snapshot))),
+            download_secret,
+            digests: Arc::default(),
+            changed: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Returns the current index snapshot
+    pub async fn snapshot(&self) -> Arc<IndexSnapshot> {
+        self.inner.read().await.clone()
+    }
+
+    /// Returns the digests known so far
+    pub async fn digests(&self) -> RwLockReadGuard<'_, Digests> {
+        self.digests.read().await
+    }
+
+    /// Returns the digest of an indexed IPA, if it has been hashed yet
+    pub async fn digest(&self, key: &CacheKey) -> Option<String> {
+        self.digests.read().await.get(key).cloned()
+    }
+
+    /// Atomically replaces the current index snapshot
+    async fn replace(&self, index: IpaIndex, app_files: AppFiles) {
+        // Keep sharing the app.json files if they didn't change
+        let current = self.snapshot().await;
+        let app_files = if *current.app_files == app_files {
+            current.app_files.clone()
+        } else {
+            Arc::new(app_files)
+        };
+
+        let snapshot = IndexSnapshot::new(
+            index,
+            app_files,
+            self.download_secret.as_deref().map(|s| s.as_str()),
+        );
+        *self.inner.write().await = Arc::new(snapshot);
+        self.changed.notify_one();
+    }
+}
+
+/// Hashes indexed IPAs in the background and publishes each digest as soon as it is known
+/// Hashing a large library takes a while, so the server starts serving without digests
+pub fn spawn_hasher(cache: Arc<IpaCache>, handle: IndexHandle) {
+    tokio::spawn(async move {
+        // Files that couldn't be hashed are not retried until they change
+        let mut failed = HashSet::new();
+
+        loop {
+            let snapshot = handle.snapshot().await;
+            let pending = pending_digests(&snapshot, &*handle.digests().await, &mut failed);
+            drop(snapshot);
+
+            if pending.is_empty() {
+                handle.changed.notified().await;
+                continue;
+            }
+
+            for key in pending {
+                // A rescan may have reused a digest that is already cached
+                let cached = cache.get(&key).await.and_then(|info| info.sha256);
+                let digest = match cached {
+                    Some(digest) => Ok(digest),
+                    None => {
+                        let path = key.0.clone();
+                        tokio::task::spawn_blocking(move || sha256_file(&path))
+                            .await
+                            .map_err(anyhow::Error::from)
+                            .and_then(|digest| digest)
+                    }
+                };
+
+                match digest {
+                    Ok(digest) => {
+                        tracing::debug!("Hashed {}", key.0.display());
+                        cache.set_sha256(&key, digest.clone()).await;
+                        handle.digests.write().await.insert(key, digest);
+                    }
+                    Err(err) => {
+                        tracing::warn!("Failed to hash {}: {:#}", key.0.display(), err);
+                        failed.insert(key);
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Lists the indexed IPAs that still need hashing
+/// Failures of files that are no longer indexed (replaced or deleted) are forgotten
+fn pending_digests(
+    snapshot: &IndexSnapshot,
+    digests: &Digests,
+    failed: &mut HashSet<CacheKey>,
+) -> Vec<CacheKey> {
+    let live: HashSet<CacheKey> = snapshot
+        .ipas
+        .values()
+        .flatten()
+        .filter(|entry| entry.bundle_identifier.is_some())
+        .map(|entry| (entry.path.clone(), entry.mtime_secs))
+        .collect();
+    failed.retain(|key| live.contains(key));
+
+    live.into_iter()
+        .filter(|key| !digests.contains_key(key) && !failed.contains(key))
+        .collect()
+}
+
+/// Which part of the index needs to be rebuilt after a batch of filesystem events
+#[derive(Debug, Default, PartialEq)]
+enum Invalidation {
+    #[default]
+    None,
+    /// Only the listed app directories changed
+    Apps(HashSet<String>),
+    /// The apps directory itself changed (or events overflowed); rescan everything
+    Full,
+}
+
+impl Invalidation {
+    /// Records the paths touched by an event
+    fn record(&mut self, apps_dir: &Path, paths: &[PathBuf]) {
+        if matches!(self, Invalidation::Full) {
+            return;
+        }
+
+        for path in paths {
+            match app_directory_of(apps_dir, path) {
+                Some(dir_name) => {
+                    if let Invalidation::Apps(dirs) = self {
+                        dirs.insert(dir_name);
+                    } else {
+                        *self = Invalidation::Apps(HashSet::from([dir_name]));
+                    }
+                }
+                None => {
+                    *self = Invalidation::Full;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Maps a changed path to the name of the app directory it belongs to
+/// Returns None if the path is the apps directory itself or outside of it
+fn app_directory_of(apps_dir: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(apps_dir).ok()?;
+    match relative.components().next()? {
+        Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+        _ => None,
+    }
+}
+
+/// Watcher on the apps directory, with the events received since it was installed
+pub struct AppsWatcher {
+    watcher: RecommendedWatcher,
+    events: mpsc::UnboundedReceiver<notify::Result<Event>>,
+}
+
+/// Starts watching the apps directory
+/// Events are queued until `spawn_indexer` consumes them, so installing the watcher before
+/// the initial scan ensures changes made while the scan runs aren't missed
+pub fn watch_apps_dir(apps_dir: &Path) -> Result<AppsWatcher> {
+    let (tx, events) = mpsc::unbounded_channel::<notify::Result<Event>>();
+
+    let mut watcher = notify::recommended_watcher(move |res| {
+        // The receiver only goes away on shutdown
+        let _ = tx.send(res);
+    })
+    .context("Failed to create filesystem watcher")?;
+
+    watcher
+        .watch(apps_dir, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch apps directory: {}", apps_dir.display()))?;
+
+    tracing::info!("Watching {} for changes", apps_dir.display());
+
+    Ok(AppsWatcher { watcher, events })
+}
+
+/// Keeps the index up to date in the background from the watcher's events
+/// Only the app directories touched by an event are rescanned
+pub fn spawn_indexer(
+    apps_dir: PathBuf,
+    cache: Arc<IpaCache>,
+    icons: IconCache,
+    handle: IndexHandle,
+    watcher: AppsWatcher,
+) {
+    let AppsWatcher {
+        watcher,
+        events: mut rx,
+    } = watcher;
+
+    tokio::spawn(async move {
+        // Keep the watcher alive for as long as the task runs
+        let _watcher = watcher;
+
+        while let Some(first) = rx.recv().await {
+            let mut invalidation = Invalidation::default();
+            apply_event(&mut invalidation, &apps_dir, first);
+
+            // Coalesce the burst of events that usually follows
+            while let Ok(Some(next)) = tokio::time::timeout(DEBOUNCE, rx.recv()).await {
+                apply_event(&mut invalidation, &apps_dir, next);
+            }
+
+            refresh(&apps_dir, &cache, &icons, &handle, invalidation).await;
+        }
+    });
+}
+
+/// Folds a single watcher event into the pending invalidation
+fn apply_event(invalidation: &mut Invalidation, apps_dir: &Path, event: notify::Result<Event>) {
+    match event {
+        Ok(event) => {
+            // Reads and metadata-only accesses don't change the index
+            if matches!(event.kind, EventKind::Access(_)) {
+                return;
+            }
+            if event.need_rescan() {
+                *invalidation = Invalidation::Full;
+                return;
+            }
+            invalidation.record(apps_dir, &event.paths);
+        }
+        Err(err) => {
+            tracing::warn!("Filesystem watcher error: {}", err);
+            *invalidation = Invalidation::Full;
+        }
+    }
+}
+
+/// Rebuilds the invalidated part of the index and swaps in the new snapshot
+async fn refresh(
+    apps_dir: &Path,
+    cache: &IpaCache,
+    icons: &IconCache,
+    handle: &IndexHandle,
+    invalidation: Invalidation,
+) {
+    let (index, app_files) = match invalidation {
+        Invalidation::None => return,
+        Invalidation::Full => match discover_ipas(apps_dir, Some(cache)).await {
+            Ok(index) => (index, discover_app_files(apps_dir)),
+            Err(err) => {
+                tracing::error!(
+                    "Failed to rescan apps directory, keeping old index: {}",
+                    err
+                );
+                return;
+            }
+        },
+        Invalidation::Apps(dirs) => {
+            let snapshot = handle.snapshot().await;
+            let mut index = snapshot.ipas.clone();
+            let mut app_files = (*snapshot.app_files).clone();
+
+            for dir_name in dirs {
+                if !is_app_directory_name(&dir_name) {
+                    continue;
+                }
+
+                let path = apps_dir.join(&dir_name);
+                match load_app_file(&path, &dir_name) {
+                    Some(app_file) => app_files.insert(dir_name.clone(), app_file),
+                    None => app_files.remove(&dir_name),
+                };
+
+                let ipa_entries = scan_app_directory(&path, &dir_name, Some(cache)).await;
+
+                if ipa_entries.is_empty() {
+                    index.remove(&dir_name);
+                } else {
+                    index.insert(dir_name, ipa_entries);
+                }
+            }
+
+            (index, app_files)
+        }
+    };
+
+    let total_ipas: usize = index.values().map(|v| v.len()).sum();
+    tracing::info!("Index updated: {} apps, {} IPAs", index.len(), total_ipas);
+
+    // Forget the metadata and icons of files that were replaced or deleted
+    let live: HashSet<CacheKey> = index
+        .values()
+        .flatten()
+        .map(|entry| (entry.path.clone(), entry.mtime_secs))
+        .collect();
+    cache.retain(&live).await;
+    icons.retain(&live).await;
+    handle
+        .digests
+        .write()
+        .await
+        .retain(|key, _| live.contains(key));
+
+    handle.replace(index, app_files).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_app_directory_of() {
+        let apps_dir = Path::new("/srv/apps");
+
+        assert_eq!(
+            app_directory_of(apps_dir, Path::new("/srv/apps/YTLite/app_1.0.ipa")),
+            Some("YTLite".to_string())
+        );
+        assert_eq!(
+            app_directory_of(apps_dir, Path::new("/srv/apps/YTLite")),
+            Some("YTLite".to_string())
+        );
+        assert_eq!(app_directory_of(apps_dir, Path::new("/srv/apps")), None);
+        assert_eq!(app_directory_of(apps_dir, Path::new("/elsewhere")), None);
+    }
+
+    #[test]
+    fn test_invalidation_collects_app_directories() {
+        let apps_dir = Path::new("/srv/apps");
+        let mut invalidation = Invalidation::default();
+
+        invalidation.record(apps_dir, &[PathBuf::from("/srv/apps/A/a.ipa")]);
+        invalidation.record(apps_dir, &[PathBuf::from("/srv/apps/B/b.ipa")]);
+
+        assert_eq!(
+            invalidation,
+            Invalidation::Apps(HashSet::from(["A".to_string(), "B".to_string()]))
+        );
+
+        // A change to the apps directory itself forces a full rescan
+        invalidation.record(apps_dir, &[PathBuf::from("/srv/apps")]);
+        assert_eq!(invalidation, Invalidation::Full);
+    }
+
+    fn entry(app_name: &str, filename: &str) -> IpaEntry {
+        IpaEntry {
+            app_name: app_name.to_string(),
+            filename: filename.to_string(),
+            path: PathBuf::from(format!("/srv/apps/{}/{}", app_name, filename)),
+            release_date: "2025-01-13T00:00:00Z".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pending_digests() {
+        let mut index = IpaIndex::new();
+        let mut hashed = entry("A", "a.ipa");
+        hashed.bundle_identifier = Some("com.example.a".to_string());
+        let mut fresh = entry("A", "b.ipa");
+        fresh.bundle_identifier = Some("com.example.a".to_string());
+        let mut broken = entry("A", "c.ipa");
+        broken.bundle_identifier = Some("com.example.a".to_string());
+        index.insert("A".to_string(), vec![hashed, fresh, broken]);
+        let snapshot = IndexSnapshot::new(index, Arc::default(), None);
+
+        let key = |filename: &str| (PathBuf::from(format!("/srv/apps/A/{}", filename)), 0);
+        let digests = Digests::from([(key("a.ipa"), "abc".to_string())]);
+        // The failure of a file that was replaced since is forgotten
+        let mut failed = HashSet::from([key("c.ipa"), (key("c.ipa").0, 1)]);
+
+        let pending = pending_digests(&snapshot, &digests, &mut failed);
+        assert_eq!(pending, vec![key("b.ipa")]);
+        assert_eq!(failed, HashSet::from([key("c.ipa")]));
+    }
+
+    #[tokio::test]
+    async fn test_replace_keeps_unchanged_app_files() {
+        let handle = IndexHandle::new(IpaIndex::new(), AppFiles::new(), None);
+        let app_files = handle.snapshot().await.app_files.clone();
+        let mut index = IpaIndex::new();
+        index.insert("A".to_string(), vec![entry("A", "a.ipa")]);
+        handle.replace(index, AppFiles::new()).await;
+
+        // Unchanged app.json files stay shared, so the merged config stays cached
+        let snapshot = handle.snapshot().await;
+        assert!(Arc::ptr_eq(&snapshot.app_files, &app_files));
+    }
+
+    #[tokio::test]
+    async fn test_index_handle_swaps_snapshot() {
+        let handle = IndexHandle::new(IpaIndex::new(), AppFiles::new(), None);
+        let before = handle.snapshot().await;
+
+        let mut index = IpaIndex::new();
+        index.insert("App".to_string(), Vec::new());
+        handle.replace(index, AppFiles::new()).await;
+
+        assert!(before.ipas.is_empty(), "Old snapshot must stay untouched");
+        assert!(handle.snapshot().await.ipas.contains_key("App"));
+    }
+
+    #[tokio::test]
+    async fn test_token_lookup_follows_snapshot() {
+        let handle = IndexHandle::new(
+            IpaIndex::new(),
+            AppFiles::new(),
+            Some(Arc::new("secret".to_string())),
+        );
+        let token = generate_download_token("App", "app_1.0.ipa", Some("secret"));
+        assert!(handle.snapshot().await.find_by_token(&token).is_none());
+
+        let mut index = IpaIndex::new();
+        index.insert(
+            "App".to_string(),
+            vec![entry("App", "app_0.9.ipa"), entry("App", "app_1.0.ipa")],
+        );
+        handle.replace(index, AppFiles::new()).await;
+
+        let snapshot = handle.snapshot().await;
+        let found = snapshot.find_by_token(&token).unwrap();
+        assert_eq!(found.filename, "app_1.0.ipa");
+        assert!(snapshot.find_by_token("unknown").is_none());
+    }
+
+    #[test]
+    fn test_no_tokens_without_secret() {
+        let mut index = IpaIndex::new();
+        index.insert("App".to_string(), vec![entry("App", "app_1.0.ipa")]);
+        let snapshot = IndexSnapshot::new(index, Arc::default(), None);
+
+        let token = generate_download_token("App", "app_1.0.ipa", None);
+        assert!(snapshot.find_by_token(&token).is_none());
+    }
+}
@@ -1,5 +1,11 @@
+use crate::macho::{self, Dylib, MachO};
+use crate::models::InjectedLibrary;
+use crate::provision::{parse_provisioning_profile, ProvisioningProfile, SigningInfo};
 use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::Path;
@@ -12,6 +18,22 @@ pub struct IpaInfo {
     pub bundle_version: String,
     pub bundle_short_version: Option<String>,
     pub bundle_name: String,
+    /// Minimum iOS version required (MinimumOSVersion)
+    pub min_os_version: Option<String>,
+    /// Archive path of the largest primary app icon, if one was found
+    pub icon_path: Option<String>,
+    /// Entitlement names from the code signature (or the provisioning profile)
+    pub entitlements: Option<Vec<String>>,
+    /// NS*UsageDescription keys and their strings from Info.plist
+    pub privacy: HashMap<String, String>,
+    /// True if the main executable is still FairPlay-encrypted (App Store DRM)
+    pub encrypted: bool,
+    /// Signing details from embedded.mobileprovision, if the IPA was pre-signed
+    pub signing: Option<SigningInfo>,
+    /// Non-Apple libraries loaded by the main executable or shipped in Frameworks/
+    pub injected_libraries: Vec<InjectedLibrary>,
+    /// Newest modification time of the archive's entries, i.e. when the IPA was built
+    pub archive_date: Option<DateTime<Utc>>,
 }
 
 /// Subset of Info.plist keys we care about
@@ -32,6 +54,63 @@ struct InfoPlist {
 
     #[serde(rename = "CFBundleDisplayName")]
     bundle_display_name: Option<String>,
+
+    #[serde(rename = "CFBundleExecutable")]
+    bundle_executable: Option<String>,
+
+    #[serde(rename = "MinimumOSVersion")]
+    minimum_os_version: Option<String>,
+
+    /// Kept loosely typed: the shape of the icon keys varies between iOS versions
+    #[serde(rename = "CFBundleIcons")]
+    bundle_icons: Option<plist::Value>,
+
+    #[serde(rename = "CFBundleIcons~ipad")]
+    bundle_icons_ipad: Option<plist::Value>,
+
+    #[serde(rename = "CFBundleIconFiles")]
+    bundle_icon_files: Option<Vec<String>>,
+
+    #[serde(rename = "CFBundleIconFile")]
+    bundle_icon_file: Option<String>,
+}
+
+impl InfoPlist {
+    /// Base names of the primary icon files, most specific keys first
+    /// e.g. "AppIcon60x60", which matches AppIcon60x60@2x.png and AppIcon60x60@3x.png
+    fn icon_base_names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+
+        for icons in [&self.bundle_icons, &self.bundle_icons_ipad]
+            .into_iter()
+            .flatten()
+        {
+            let Some(primary) = icons
+                .as_dictionary()
+                .and_then(|icons| icons.get("CFBundlePrimaryIcon"))
+                .and_then(|primary| primary.as_dictionary())
+            else {
+                continue;
+            };
+
+            if let Some(files) = primary.get("CFBundleIconFiles").and_then(|f| f.as_array()) {
+                names.extend(files.iter().filter_map(|f| f.as_string()).map(String::from));
+            }
+            if let Some(name) = primary.get("CFBundleIconName").and_then(|n| n.as_string()) {
+                names.push(name.to_string());
+            }
+        }
+
+        names.extend(self.bundle_icon_files.iter().flatten().cloned());
+        names.extend(self.bundle_icon_file.clone());
+
+        // Some bundles list names with their extension
+        names
+            .into_iter()
+            .map(|name| name.trim_end_matches(".png").to_string())
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
 }
 
 /// Extract Info.plist from an IPA file
@@ -47,17 +126,90 @@ pub fn extract_ipa_info(ipa_path: &Path) -> Result<IpaInfo> {
     let plist_path = find_info_plist(&mut archive)?;
 
     // Read the Info.plist file
-    let mut plist_file = archive
-        .by_name(&plist_path)
-        .context("Failed to read Info.plist from IPA")?;
-
-    let mut plist_data = Vec::new();
-    plist_file
-        .read_to_end(&mut plist_data)
-        .context("Failed to read Info.plist contents")?;
+    let plist_data = read_entry(&mut archive, &plist_path)?;
 
     // Parse the plist
     let info: InfoPlist = plist::from_bytes(&plist_data).context("Failed to parse Info.plist")?;
+    let privacy = usage_descriptions(&plist_data)?;
+
+    // Icons live at the top level of the .app bundle, next to Info.plist
+    let bundle_dir = plist_path.trim_end_matches("Info.plist");
+    let icon_path = find_primary_icon(&mut archive, bundle_dir, &info.icon_base_names());
+
+    // The main executable is named by CFBundleExecutable, defaulting to the bundle name
+    let executable = info.bundle_executable.clone().unwrap_or_else(|| {
+        bundle_dir
+            .trim_end_matches(".app/")
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    });
+    let binary = match read_entry(&mut archive, &format!("{}{}", bundle_dir, executable)) {
+        Ok(binary) => Some(binary),
+        Err(err) => {
+            tracing::warn!(
+                "Failed to read main executable from {}: {:#}",
+                ipa_path.display(),
+                err
+            );
+            None
+        }
+    };
+
+    let encrypted = binary
+        .as_deref()
+        .is_some_and(|binary| match is_encrypted(binary) {
+            Ok(encrypted) => encrypted,
+            Err(err) => {
+                tracing::warn!(
+                    "Failed to check encryption of {}: {:#}",
+                    ipa_path.display(),
+                    err
+                );
+                false
+            }
+        });
+
+    let injected_libraries = match injected_libraries(&mut archive, bundle_dir, binary.as_deref()) {
+        Ok(libraries) => libraries,
+        Err(err) => {
+            tracing::warn!(
+                "Failed to list injected libraries of {}: {:#}",
+                ipa_path.display(),
+                err
+            );
+            Vec::new()
+        }
+    };
+
+    // Pre-signed IPAs carry the provisioning profile they were signed with
+    let profile = match read_profile(&mut archive, bundle_dir) {
+        Ok(profile) => profile,
+        Err(err) => {
+            tracing::warn!(
+                "Failed to read provisioning profile from {}: {:#}",
+                ipa_path.display(),
+                err
+            );
+            None
+        }
+    };
+    let signing = profile.as_ref().map(ProvisioningProfile::signing_info);
+
+    let entitlements = match read_entitlements(binary.as_deref(), profile.as_ref()) {
+        Ok(entitlements) => entitlements,
+        Err(err) => {
+            tracing::warn!(
+                "Failed to read entitlements from {}: {:#}",
+                ipa_path.display(),
+                err
+            );
+            None
+        }
+    };
+
+    let archive_date = newest_entry_date(&mut archive);
 
     // Extract required fields
     let bundle_identifier = info
@@ -78,9 +230,287 @@ pub fn extract_ipa_info(ipa_path: &Path) -> Result<IpaInfo> {
         bundle_version,
         bundle_short_version: info.bundle_short_version,
         bundle_name,
+        min_os_version: info.minimum_os_version,
+        icon_path,
+        entitlements,
+        privacy,
+        encrypted,
+        signing,
+        injected_libraries,
+        archive_date,
     })
 }
 
+/// Finds the newest modification time among the archive's entries
+/// ZIP timestamps carry no time zone; they are taken as UTC. Entries stamped with the
+/// DOS epoch (1980-01-01, written by reproducible builds) carry no information and are ignored.
+fn newest_entry_date(archive: &mut ZipArchive<BufReader<File>>) -> Option<DateTime<Utc>> {
+    (0..archive.len())
+        .filter_map(|i| archive.by_index_raw(i).ok()?.last_modified())
+        .filter(|modified| modified.year() > 1980)
+        .filter_map(|modified| {
+            NaiveDate::from_ymd_opt(
+                modified.year().into(),
+                modified.month().into(),
+                modified.day().into(),
+            )?
+            .and_hms_opt(
+                modified.hour().into(),
+                modified.minute().into(),
+                modified.second().into(),
+            )
+        })
+        .max()
+        .map(|newest| newest.and_utc())
+}
+
+/// Collects the NS*UsageDescription keys of an Info.plist
+/// These are the strings iOS shows when the app asks for a permission
+fn usage_descriptions(plist_data: &[u8]) -> Result<HashMap<String, String>> {
+    let dictionary: plist::Dictionary =
+        plist::from_bytes(plist_data).context("Failed to parse Info.plist")?;
+
+    Ok(dictionary
+        .into_iter()
+        .filter(|(key, _)| key.starts_with("NS") && key.ends_with("UsageDescription"))
+        .filter_map(|(key, value)| Some((key, value.into_string()?)))
+        .collect())
+}
+
+/// Reads the entitlement names granted to the app
+/// The main executable's code signature is authoritative; embedded.mobileprovision is
+/// only consulted when the binary carries no entitlements blob
+fn read_entitlements(
+    binary: Option<&[u8]>,
+    profile: Option<&ProvisioningProfile>,
+) -> Result<Option<Vec<String>>> {
+    if let Some(binary) = binary {
+        if let Some(entitlements) = binary_entitlements(binary)? {
+            return Ok(Some(entitlements));
+        }
+    }
+
+    Ok(profile.map(|profile| sorted_keys(&profile.entitlements)))
+}
+
+/// Reads and parses embedded.mobileprovision, if the bundle has one
+fn read_profile(
+    archive: &mut ZipArchive<BufReader<File>>,
+    bundle_dir: &str,
+) -> Result<Option<ProvisioningProfile>> {
+    let profile_path = format!("{}embedded.mobileprovision", bundle_dir);
+    if archive.index_for_name(&profile_path).is_none() {
+        return Ok(None);
+    }
+
+    parse_provisioning_profile(&read_entry(archive, &profile_path)?).map(Some)
+}
+
+/// Entitlement names from the code signature of the first slice that has any
+fn binary_entitlements(binary: &[u8]) -> Result<Option<Vec<String>>> {
+    for slice in macho::slices(binary)? {
+        if let Some(plist) = MachO::parse(slice)?.entitlements()? {
+            let entitlements: plist::Dictionary =
+                plist::from_bytes(plist).context("Failed to parse entitlements plist")?;
+            return Ok(Some(sorted_keys(&entitlements)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Lists the tweak libraries of an app: non-Apple dylibs and frameworks the main executable
+/// loads, plus the dylibs dropped into Frameworks/ (which may be loaded by another tweak).
+/// The IPA doesn't say what was added after the fact, so the app's own frameworks are listed too
+fn injected_libraries(
+    archive: &mut ZipArchive<BufReader<File>>,
+    bundle_dir: &str,
+    binary: Option<&[u8]>,
+) -> Result<Vec<InjectedLibrary>> {
+    let mut libraries: Vec<InjectedLibrary> = Vec::new();
+
+    // All slices of a universal binary load the same libraries
+    if let Some(slice) = binary
+        .map(macho::slices)
+        .transpose()?
+        .and_then(|s| s.first().copied())
+    {
+        for dylib in MachO::parse(slice)?.load_dylibs()? {
+            if !is_apple_library(dylib.path) {
+                libraries.push(library_of(&dylib));
+            }
+        }
+    }
+
+    let frameworks_dir = format!("{}Frameworks/", bundle_dir);
+    let bundled: Vec<String> = archive
+        .file_names()
+        .filter(|name| {
+            name.strip_prefix(&frameworks_dir)
+                .is_some_and(|file| file.ends_with(".dylib") && !file.contains('/'))
+        })
+        .map(String::from)
+        .collect();
+
+    for path in bundled {
+        if is_apple_library(&path) {
+            continue;
+        }
+
+        // The dylib's own LC_ID_DYLIB carries its version
+        let data = match read_entry(archive, &path) {
+            Ok(data) => data,
+            Err(e) => {
+                tracing::debug!("Skipping unreadable library {}: {:#}", path, e);
+                continue;
+            }
+        };
+        let own = macho::slices(&data)
+            .ok()
+            .and_then(|slices| slices.first().and_then(|s| MachO::parse(s).ok()))
+            .and_then(|macho| macho.id_dylib().ok().flatten())
+            .map(|dylib| library_of(&dylib));
+        let library = own.unwrap_or_else(|| InjectedLibrary {
+            name: file_name(&path).to_string(),
+            version: None,
+        });
+
+        match libraries.iter_mut().find(|l| l.name == library.name) {
+            Some(existing) => {
+                if existing.version.is_none() {
+                    existing.version = library.version;
+                }
+            }
+            None => libraries.push(library),
+        }
+    }
+
+    libraries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(libraries)
+}
+
+/// System libraries and the Swift runtime ship with every app and aren't worth listing
+fn is_apple_library(path: &str) -> bool {
+    path.starts_with("/System/")
+        || path.starts_with("/usr/lib/")
+        || file_name(path).starts_with("libswift")
+}
+
+/// Names a library after its file, or after its bundle for frameworks
+/// e.g. "@rpath/Orion.framework/Orion" -> "Orion.framework"
+fn library_of(dylib: &Dylib) -> InjectedLibrary {
+    let name = dylib
+        .path
+        .rsplit('/')
+        .find(|component| component.ends_with(".framework"))
+        .unwrap_or_else(|| file_name(dylib.path));
+
+    // A packed version of 0 means the library doesn't declare one
+    let version = (dylib.current_version != 0).then(|| {
+        format!(
+            "{}.{}.{}",
+            dylib.current_version >> 16,
+            (dylib.current_version >> 8) & 0xff,
+            dylib.current_version & 0xff
+        )
+    });
+
+    InjectedLibrary {
+        name: name.to_string(),
+        version,
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Returns true if any architecture slice of the binary is FairPlay-encrypted
+fn is_encrypted(binary: &[u8]) -> Result<bool> {
+    for slice in macho::slices(binary)? {
+        if MachO::parse(slice)?.is_encrypted()? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+fn sorted_keys(dictionary: &plist::Dictionary) -> Vec<String> {
+    let mut keys: Vec<String> = dictionary.keys().cloned().collect();
+    keys.sort();
+    keys
+}
+
+/// Find the largest PNG in the bundle directory matching one of the icon base names
+/// Larger files are higher resolution variants (@3x over @2x)
+fn find_primary_icon(
+    archive: &mut ZipArchive<BufReader<File>>,
+    bundle_dir: &str,
+    base_names: &[String],
+) -> Option<String> {
+    let mut best: Option<(u64, String)> = None;
+
+    for i in 0..archive.len() {
+        let Ok(file) = archive.by_index(i) else {
+            continue;
+        };
+        let name = file.name();
+
+        let Some(file_name) = name.strip_prefix(bundle_dir) else {
+            continue;
+        };
+        if file_name.contains('/') || !file_name.ends_with(".png") {
+            continue;
+        }
+        if !base_names
+            .iter()
+            .any(|base| file_name.starts_with(base.as_str()))
+        {
+            continue;
+        }
+
+        if best.as_ref().is_none_or(|(size, _)| file.size() > *size) {
+            best = Some((file.size(), name.to_string()));
+        }
+    }
+
+    best.map(|(_, name)| name)
+}
+
+/// Read a single file from an IPA archive
+pub fn read_archive_entry(ipa_path: &Path, entry_name: &str) -> Result<Vec<u8>> {
+    let file = File::open(ipa_path)
+        .with_context(|| format!("Failed to open IPA file: {}", ipa_path.display()))?;
+    let mut archive =
+        ZipArchive::new(BufReader::new(file)).context("Failed to read IPA as ZIP archive")?;
+
+    read_entry(&mut archive, entry_name)
+}
+
+/// Read a single file from an already opened IPA archive
+fn read_entry(archive: &mut ZipArchive<BufReader<File>>, entry_name: &str) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    archive
+        .by_name(entry_name)
+        .with_context(|| format!("{} not found in IPA archive", entry_name))?
+        .read_to_end(&mut data)
+        .with_context(|| format!("Failed to read {} from IPA", entry_name))?;
+
+    Ok(data)
+}
+
+/// Compute the hex-encoded SHA-256 of a file, streaming it from disk
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open IPA file: {}", path.display()))?;
+
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).context("Failed to read IPA file for hashing")?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
 /// Find the Info.plist file within the IPA archive
 fn find_info_plist(archive: &mut ZipArchive<BufReader<File>>) -> Result<String> {
     for i in 0..archive.len() {
@@ -98,9 +528,159 @@ fn find_info_plist(archive: &mut ZipArchive<BufReader<File>>) -> Result<String>
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn test_plist_path_detection() {
         // This is a unit test - we'd need a real IPA file to test extraction
         // For now, just ensure the module compiles
     }
+
+    #[test]
+    fn test_icon_base_names() {
+        let plist = br#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+    <key>CFBundleIcons</key>
+    <dict>
+        <key>CFBundlePrimaryIcon</key>
+        <dict>
+            <key>CFBundleIconFiles</key>
+            <array><string>AppIcon60x60</string></array>
+            <key>CFBundleIconName</key>
+            <string>AppIcon</string>
+        </dict>
+    </dict>
+    <key>CFBundleIconFile</key>
+    <string>Icon.png</string>
+</dict>
+</plist>"#;
+
+        let info: InfoPlist = plist::from_bytes(plist).unwrap();
+        assert_eq!(
+            info.icon_base_names(),
+            vec!["AppIcon60x60", "AppIcon", "Icon"]
+        );
+    }
+
+    #[test]
+    fn test_usage_descriptions() {
+        let plist = r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+    <key>CFBundleIdentifier</key>
+    <string>com.example.app</string>
+    <key>NSCameraUsageDescription</key>
+    <string>Scan QR codes</string>
+    <key>NSLocationWhenInUseUsageDescription</key>
+    <string>Show nearby places</string>
+    <key>NSAppTransportSecurity</key>
+    <dict/>
+</dict>
+</plist>"#;
+
+        let privacy = usage_descriptions(plist.as_bytes()).unwrap();
+        assert_eq!(privacy.len(), 2);
+        assert_eq!(privacy["NSCameraUsageDescription"], "Scan QR codes");
+        assert_eq!(
+            privacy["NSLocationWhenInUseUsageDescription"],
+            "Show nearby places"
+        );
+    }
+
+    #[test]
+    fn test_binary_entitlements() {
+        let binary = crate::macho::tests::thin_binary(&[], Some(crate::macho::tests::ENTITLEMENTS));
+        assert_eq!(
+            binary_entitlements(&binary).unwrap(),
+            Some(vec![
+                "application-identifier".to_string(),
+                "get-task-allow".to_string()
+            ])
+        );
+
+        let unsigned = crate::macho::tests::thin_binary(&[], None);
+        assert_eq!(binary_entitlements(&unsigned).unwrap(), None);
+    }
+
+    #[test]
+    fn test_library_of() {
+        let library = |path, current_version| {
+            let InjectedLibrary { name, version } = library_of(&Dylib {
+                path,
+                current_version,
+            });
+            (name, version)
+        };
+
+        assert_eq!(
+            library("@executable_path/Frameworks/YTLite.dylib", 0x0005_0201),
+            ("YTLite.dylib".to_string(), Some("5.2.1".to_string()))
+        );
+        assert_eq!(
+            library("@rpath/Orion.framework/Orion", 0),
+            ("Orion.framework".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn test_is_apple_library() {
+        assert!(is_apple_library("/usr/lib/libobjc.A.dylib"));
+        assert!(is_apple_library(
+            "/System/Library/Frameworks/UIKit.framework/UIKit"
+        ));
+        assert!(is_apple_library("@rpath/libswiftCore.dylib"));
+        assert!(!is_apple_library("@rpath/YTLite.dylib"));
+    }
+
+    #[test]
+    fn test_injected_libraries_skip_unreadable_dylibs() {
+        use std::io::Write;
+        use zip::write::SimpleFileOptions;
+
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for name in ["Broken.dylib", "YTLite.dylib"] {
+            zip.start_file(format!("Payload/App.app/Frameworks/{}", name), options)
+                .unwrap();
+            zip.write_all(format!("{}-contents", name).as_bytes())
+                .unwrap();
+        }
+        let mut data = zip.finish().unwrap().into_inner();
+
+        // Corrupt the stored bytes so the CRC check fails when reading
+        let needle = b"Broken.dylib-contents";
+        let at = data
+            .windows(needle.len())
+            .position(|w| w == needle)
+            .unwrap();
+        data[at] = b'X';
+
+        let path = std::env::temp_dir().join(format!("injected-{}.ipa", std::process::id()));
+        std::fs::write(&path, data).unwrap();
+        let mut archive = ZipArchive::new(BufReader::new(File::open(&path).unwrap())).unwrap();
+
+        let libraries = injected_libraries(&mut archive, "Payload/App.app/", None).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(
+            libraries,
+            vec![InjectedLibrary {
+                name: "YTLite.dylib".to_string(),
+                version: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_is_encrypted_checks_every_slice() {
+        use crate::macho::tests::{encryption_command, fat_binary, thin_binary};
+
+        let decrypted = thin_binary(&[encryption_command(0)], None);
+        let encrypted = thin_binary(&[encryption_command(1)], None);
+
+        assert!(!is_encrypted(&fat_binary(&[decrypted.clone(), decrypted.clone()])).unwrap());
+        assert!(is_encrypted(&fat_binary(&[decrypted, encrypted])).unwrap());
+    }
 }
@@ -0,0 +1,470 @@
+use anyhow::{Context, Result};
+
+/// Universal (fat) binary magics, always stored big-endian
+const FAT_MAGIC: u32 = 0xcafe_babe;
+const FAT_MAGIC_64: u32 = 0xcafe_babf;
+
+/// Thin Mach-O magics, as read in the binary's own byte order
+const MH_MAGIC: u32 = 0xfeed_face;
+const MH_MAGIC_64: u32 = 0xfeed_facf;
+
+/// Load command pointing at the code signature in __LINKEDIT
+const LC_CODE_SIGNATURE: u32 = 0x1d;
+
+/// Load commands naming a linked dynamic library
+const LC_LOAD_DYLIB: u32 = 0x0c;
+const LC_LOAD_WEAK_DYLIB: u32 = 0x8000_0018;
+
+/// Load command naming a dynamic library itself
+const LC_ID_DYLIB: u32 = 0x0d;
+
+/// Load commands describing the FairPlay-encrypted range of the binary
+const LC_ENCRYPTION_INFO: u32 = 0x21;
+const LC_ENCRYPTION_INFO_64: u32 = 0x2c;
+
+/// Code signature blob magics (always big-endian)
+const CSMAGIC_EMBEDDED_SIGNATURE: u32 = 0xfade_0cc0;
+const CSMAGIC_EMBEDDED_ENTITLEMENTS: u32 = 0xfade_7171;
+
+/// Superblob slot holding the XML entitlements plist
+const CSSLOT_ENTITLEMENTS: u32 = 5;
+
+/// Splits a Mach-O file into its architecture slices
+/// A thin binary is returned as a single slice.
+pub fn slices(data: &[u8]) -> Result<Vec<&[u8]>> {
+    let magic = read_u32(data, 0, true)?;
+    let is_64 = match magic {
+        FAT_MAGIC => false,
+        FAT_MAGIC_64 => true,
+        _ => return Ok(vec![data]),
+    };
+
+    let count = read_u32(data, 4, true)? as usize;
+    let arch_size = if is_64 { 32 } else { 20 };
+    // The count comes straight from the file; don't let it drive an allocation
+    if count.saturating_mul(arch_size) > data.len() - 8 {
+        anyhow::bail!("Universal binary claims {} architectures", count);
+    }
+    let mut slices = Vec::with_capacity(count);
+
+    for i in 0..count {
+        let arch = 8 + i * arch_size;
+        let (offset, size) = if is_64 {
+            (
+                read_u64(data, arch + 8, true)?,
+                read_u64(data, arch + 16, true)?,
+            )
+        } else {
+            (
+                read_u32(data, arch + 8, true)? as u64,
+                read_u32(data, arch + 12, true)? as u64,
+            )
+        };
+
+        slices.push(
+            sub_slice(data, offset as usize, size as usize).with_context(|| {
+                format!("Architecture {} extends past the end of the binary", i)
+            })?,
+        );
+    }
+
+    Ok(slices)
+}
+
+/// A single load command, including its cmd/cmdsize header
+pub struct LoadCommand<'a> {
+    pub cmd: u32,
+    pub data: &'a [u8],
+}
+
+/// A dynamic library named by a dylib load command
+pub struct Dylib<'a> {
+    /// Install name, e.g. "@rpath/Foo.framework/Foo" or "/usr/lib/libobjc.A.dylib"
+    pub path: &'a str,
+    /// Packed as xxxx.yy.zz (major in the upper 16 bits)
+    pub current_version: u32,
+}
+
+/// A parsed thin (single architecture) Mach-O binary
+pub struct MachO<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+    commands: Vec<LoadCommand<'a>>,
+}
+
+impl<'a> MachO<'a> {
+    /// Parses the header and load commands of a thin Mach-O slice
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let (big_endian, is_64) = match (read_u32(data, 0, false)?, read_u32(data, 0, true)?) {
+            (MH_MAGIC, _) => (false, false),
+            (MH_MAGIC_64, _) => (false, true),
+            (_, MH_MAGIC) => (true, false),
+            (_, MH_MAGIC_64) => (true, true),
+            _ => anyhow::bail!("Not a Mach-O binary"),
+        };
+
+        let count = read_u32(data, 16, big_endian)? as usize;
+        let mut offset = if is_64 { 32 } else { 28 };
+        // Each load command takes at least 8 bytes, so a larger count can't be genuine
+        if count.saturating_mul(8) > data.len().saturating_sub(offset) {
+            anyhow::bail!("Mach-O header claims {} load commands", count);
+        }
+        let mut commands = Vec::with_capacity(count);
+
+        for _ in 0..count {
+            let cmd = read_u32(data, offset, big_endian)?;
+            let size = read_u32(data, offset + 4, big_endian)? as usize;
+            if size < 8 {
+                anyhow::bail!("Invalid load command size {}", size);
+            }
+
+            let command =
+                sub_slice(data, offset, size).context("Load command extends past the binary")?;
+            commands.push(LoadCommand { cmd, data: command });
+            offset += size;
+        }
+
+        Ok(Self {
+            data,
+            big_endian,
+            commands,
+        })
+    }
+
+    /// Reads a u32 field of a load command in the binary's byte order
+    pub fn command_u32(&self, command: &LoadCommand, offset: usize) -> Result<u32> {
+        read_u32(command.data, offset, self.big_endian)
+    }
+
+    /// Returns the libraries the binary links against, including weak links
+    pub fn load_dylibs(&self) -> Result<Vec<Dylib<'a>>> {
+        self.commands
+            .iter()
+            .filter(|command| command.cmd == LC_LOAD_DYLIB || command.cmd == LC_LOAD_WEAK_DYLIB)
+            .map(|command| self.dylib(command))
+            .collect()
+    }
+
+    /// Returns the library's own install name and version, if the binary is a dylib
+    pub fn id_dylib(&self) -> Result<Option<Dylib<'a>>> {
+        self.commands
+            .iter()
+            .find(|command| command.cmd == LC_ID_DYLIB)
+            .map(|command| self.dylib(command))
+            .transpose()
+    }
+
+    /// Decodes a dylib_command: name offset, timestamp, current and compatibility versions
+    fn dylib(&self, command: &LoadCommand<'a>) -> Result<Dylib<'a>> {
+        let name_offset = self.command_u32(command, 8)? as usize;
+        let current_version = self.command_u32(command, 16)?;
+
+        let name = command
+            .data
+            .get(name_offset..)
+            .context("Dylib name lies outside its load command")?;
+        let name = name.split(|&byte| byte == 0).next().unwrap_or_default();
+        let path = std::str::from_utf8(name).context("Dylib name is not valid UTF-8")?;
+
+        Ok(Dylib {
+            path,
+            current_version,
+        })
+    }
+
+    /// Returns true if the binary is FairPlay-encrypted (App Store DRM)
+    /// A non-zero cryptid means the encrypted range has not been decrypted
+    pub fn is_encrypted(&self) -> Result<bool> {
+        for command in &self.commands {
+            let is_encryption_info =
+                command.cmd == LC_ENCRYPTION_INFO || command.cmd == LC_ENCRYPTION_INFO_64;
+            if is_encryption_info && self.command_u32(command, 16)? != 0 {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Returns the raw entitlements plist embedded in the code signature
+    /// None if the binary is unsigned or signed without entitlements
+    pub fn entitlements(&self) -> Result<Option<&'a [u8]>> {
+        let Some(command) = self
+            .commands
+            .iter()
+            .find(|command| command.cmd == LC_CODE_SIGNATURE)
+        else {
+            return Ok(None);
+        };
+
+        let offset = self.command_u32(command, 8)? as usize;
+        let size = self.command_u32(command, 12)? as usize;
+        let signature =
+            sub_slice(self.data, offset, size).context("Code signature extends past the binary")?;
+
+        if read_u32(signature, 0, true)? != CSMAGIC_EMBEDDED_SIGNATURE {
+            anyhow::bail!("Code signature is not an embedded signature superblob");
+        }
+
+        let count = read_u32(signature, 8, true)? as usize;
+        for i in 0..count {
+            let index = 12 + i * 8;
+            if read_u32(signature, index, true)? != CSSLOT_ENTITLEMENTS {
+                continue;
+            }
+
+            let blob = read_u32(signature, index + 4, true)? as usize;
+            if read_u32(signature, blob, true)? != CSMAGIC_EMBEDDED_ENTITLEMENTS {
+                anyhow::bail!("Entitlements slot does not contain an entitlements blob");
+            }
+
+            let length = read_u32(signature, blob + 4, true)? as usize;
+            let plist = length
+                .checked_sub(8)
+                .and_then(|len| sub_slice(signature, blob + 8, len))
+                .context("Entitlements blob extends past the code signature")?;
+            return Ok(Some(plist));
+        }
+
+        Ok(None)
+    }
+}
+
+/// Bounds-checked `&data[offset..offset + len]`
+fn sub_slice(data: &[u8], offset: usize, len: usize) -> Option<&[u8]> {
+    data.get(offset..offset.checked_add(len)?)
+}
+
+fn read_u32(data: &[u8], offset: usize, big_endian: bool) -> Result<u32> {
+    let bytes: [u8; 4] = sub_slice(data, offset, 4)
+        .context("Unexpected end of Mach-O data")?
+        .try_into()?;
+    Ok(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+fn read_u64(data: &[u8], offset: usize, big_endian: bool) -> Result<u64> {
+    let bytes: [u8; 8] = sub_slice(data, offset, 8)
+        .context("Unexpected end of Mach-O data")?
+        .try_into()?;
+    Ok(if big_endian {
+        u64::from_be_bytes(bytes)
+    } else {
+        u64::from_le_bytes(bytes)
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub const ENTITLEMENTS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+    <key>application-identifier</key>
+    <string>TEAMID.com.example.app</string>
+    <key>get-task-allow</key>
+    <true/>
+</dict>
+</plist>"#;
+
+    /// Builds a little-endian 64-bit Mach-O with the given extra load commands
+    /// and a code signature carrying `entitlements`
+    pub fn thin_binary(extra_commands: &[Vec<u8>], entitlements: Option<&str>) -> Vec<u8> {
+        let signature_command_size = 16;
+        let commands_size: usize =
+            extra_commands.iter().map(Vec::len).sum::<usize>() + signature_command_size;
+        let signature_offset = 32 + commands_size;
+
+        let mut signature = Vec::new();
+        let slots: Vec<(u32, Vec<u8>)> = entitlements
+            .map(|plist| {
+                let mut blob = CSMAGIC_EMBEDDED_ENTITLEMENTS.to_be_bytes().to_vec();
+                blob.extend_from_slice(&(plist.len() as u32 + 8).to_be_bytes());
+                blob.extend_from_slice(plist.as_bytes());
+                vec![(CSSLOT_ENTITLEMENTS, blob)]
+            })
+            .unwrap_or_default();
+
+        let header_len = 12 + slots.len() * 8;
+        let total: usize = header_len + slots.iter().map(|(_, b)| b.len()).sum::<usize>();
+        signature.extend_from_slice(&CSMAGIC_EMBEDDED_SIGNATURE.to_be_bytes());
+        signature.extend_from_slice(&(total as u32).to_be_bytes());
+        signature.extend_from_slice(&(slots.len() as u32).to_be_bytes());
+        let mut blob_offset = header_len;
+        for (slot, blob) in &slots {
+            signature.extend_from_slice(&slot.to_be_bytes());
+            signature.extend_from_slice(&(blob_offset as u32).to_be_bytes());
+            blob_offset += blob.len();
+        }
+        for (_, blob) in &slots {
+            signature.extend_from_slice(blob);
+        }
+
+        let mut binary = Vec::new();
+        for field in [
+            MH_MAGIC_64,
+            0x0100_000c,
+            0,
+            2,
+            extra_commands.len() as u32 + 1,
+            commands_size as u32,
+            0,
+            0,
+        ] {
+            binary.extend_from_slice(&field.to_le_bytes());
+        }
+        for command in extra_commands {
+            binary.extend_from_slice(command);
+        }
+        for field in [
+            LC_CODE_SIGNATURE,
+            signature_command_size as u32,
+            signature_offset as u32,
+            signature.len() as u32,
+        ] {
+            binary.extend_from_slice(&field.to_le_bytes());
+        }
+        binary.extend_from_slice(&signature);
+        binary
+    }
+
+    /// Builds an LC_ENCRYPTION_INFO_64 load command with the given cryptid
+    pub fn encryption_command(cryptid: u32) -> Vec<u8> {
+        [LC_ENCRYPTION_INFO_64, 24, 0x4000, 0x8000, cryptid, 0]
+            .iter()
+            .flat_map(|field| field.to_le_bytes())
+            .collect()
+    }
+
+    /// Builds a dylib load command (LC_LOAD_DYLIB, LC_ID_DYLIB, ...) padded to 8 bytes
+    pub fn dylib_command(cmd: u32, path: &str, current_version: u32) -> Vec<u8> {
+        let size = (24 + path.len() + 1).next_multiple_of(8);
+        let mut command: Vec<u8> = [cmd, size as u32, 24, 0, current_version, 0x0001_0000]
+            .iter()
+            .flat_map(|field| field.to_le_bytes())
+            .collect();
+        command.extend_from_slice(path.as_bytes());
+        command.resize(size, 0);
+        command
+    }
+
+    /// Wraps thin binaries into a 32-bit fat header
+    pub fn fat_binary(thin: &[Vec<u8>]) -> Vec<u8> {
+        let mut binary = FAT_MAGIC.to_be_bytes().to_vec();
+        binary.extend_from_slice(&(thin.len() as u32).to_be_bytes());
+
+        let mut offset = 8 + thin.len() * 20;
+        for slice in thin {
+            for field in [0x0100_000c, 0, offset as u32, slice.len() as u32, 0] {
+                binary.extend_from_slice(&field.to_be_bytes());
+            }
+            offset += slice.len();
+        }
+        for slice in thin {
+            binary.extend_from_slice(slice);
+        }
+        binary
+    }
+
+    #[test]
+    fn test_entitlements_from_thin_binary() {
+        let binary = thin_binary(&[], Some(ENTITLEMENTS));
+        let slices = slices(&binary).unwrap();
+        assert_eq!(slices.len(), 1);
+
+        let macho = MachO::parse(slices[0]).unwrap();
+        assert_eq!(macho.entitlements().unwrap(), Some(ENTITLEMENTS.as_bytes()));
+    }
+
+    #[test]
+    fn test_entitlements_from_fat_binary() {
+        let thin = thin_binary(&[], Some(ENTITLEMENTS));
+        let binary = fat_binary(&[thin.clone(), thin]);
+
+        let slices = slices(&binary).unwrap();
+        assert_eq!(slices.len(), 2);
+        for slice in slices {
+            let macho = MachO::parse(slice).unwrap();
+            assert_eq!(macho.entitlements().unwrap(), Some(ENTITLEMENTS.as_bytes()));
+        }
+    }
+
+    #[test]
+    fn test_signature_without_entitlements() {
+        let binary = thin_binary(&[], None);
+        let macho = MachO::parse(&binary).unwrap();
+        assert_eq!(macho.entitlements().unwrap(), None);
+    }
+
+    #[test]
+    fn test_encryption_info() {
+        let encrypted = thin_binary(&[encryption_command(1)], None);
+        assert!(MachO::parse(&encrypted).unwrap().is_encrypted().unwrap());
+
+        let decrypted = thin_binary(&[encryption_command(0)], None);
+        assert!(!MachO::parse(&decrypted).unwrap().is_encrypted().unwrap());
+
+        let plain = thin_binary(&[], None);
+        assert!(!MachO::parse(&plain).unwrap().is_encrypted().unwrap());
+    }
+
+    #[test]
+    fn test_dylibs() {
+        let binary = thin_binary(
+            &[
+                dylib_command(LC_ID_DYLIB, "@rpath/Tweak.dylib", 0x0001_0203),
+                dylib_command(LC_LOAD_DYLIB, "/usr/lib/libobjc.A.dylib", 0x00e4_0000),
+                dylib_command(LC_LOAD_WEAK_DYLIB, "@rpath/Orion.framework/Orion", 0),
+            ],
+            None,
+        );
+        let macho = MachO::parse(&binary).unwrap();
+
+        let loaded: Vec<(&str, u32)> = macho
+            .load_dylibs()
+            .unwrap()
+            .iter()
+            .map(|dylib| (dylib.path, dylib.current_version))
+            .collect();
+        assert_eq!(
+            loaded,
+            vec![
+                ("/usr/lib/libobjc.A.dylib", 0x00e4_0000),
+                ("@rpath/Orion.framework/Orion", 0)
+            ]
+        );
+
+        let id = macho.id_dylib().unwrap().unwrap();
+        assert_eq!(id.path, "@rpath/Tweak.dylib");
+        assert_eq!(id.current_version, 0x0001_0203);
+    }
+
+    #[test]
+    fn test_truncated_binary_is_rejected() {
+        let binary = thin_binary(&[], Some(ENTITLEMENTS));
+        assert!(MachO::parse(&binary[..40]).is_err());
+        assert!(MachO::parse(b"not a binary").is_err());
+    }
+
+    #[test]
+    fn test_oversized_counts_are_rejected() {
+        // A bare 64-bit header claiming 0xffffffff load commands
+        let mut header = Vec::new();
+        header.extend_from_slice(&MH_MAGIC_64.to_le_bytes());
+        header.extend_from_slice(&[0; 12]);
+        header.extend_from_slice(&u32::MAX.to_le_bytes());
+        header.extend_from_slice(&[0; 12]);
+        assert_eq!(header.len(), 32);
+        assert!(MachO::parse(&header).is_err());
+
+        // A universal header claiming 0xffffffff architectures
+        let mut fat = Vec::new();
+        fat.extend_from_slice(&FAT_MAGIC.to_be_bytes());
+        fat.extend_from_slice(&u32::MAX.to_be_bytes());
+        assert!(slices(&fat).is_err());
+    }
+}
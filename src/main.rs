@@ -1,12 +1,25 @@
 mod auth;
 mod cache;
+mod config;
+mod diagnostics;
 mod discovery;
 mod generator;
+mod icon;
+mod indexer;
 mod ipa_info;
+mod macho;
+mod markdown;
+mod matching;
 mod models;
+mod pattern;
+mod provision;
+mod reload;
 mod routes;
+mod sidecar;
 mod state;
 mod token;
+mod token_store;
+mod version;
 
 use anyhow::{Context, Result};
 use axum::{
@@ -15,11 +28,15 @@ use axum::{
     routing::get,
     Router,
 };
-use cache::IpaCache;
+use cache::{IconCache, IpaCache};
 use clap::Parser;
-use discovery::discover_ipas;
+use config::{ConfigHandle, MergedConfigCache};
+use discovery::{discover_app_files, discover_ipas};
+use generator::CatalogCache;
+use indexer::IndexHandle;
 use state::AppState;
-use std::{path::PathBuf, sync::Arc};
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use token_store::TokenStoreHandle;
 use tower_http::cors::{Any, CorsLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
@@ -43,9 +60,21 @@ struct Args {
     #[arg(long, env = "AUTH_TOKEN")]
     auth_token: Option<String>,
 
-    /// Optional secret key for generating obfuscated download URLs
+    /// Optional JSON file with named API tokens and per-app scopes
+    #[arg(long, env = "TOKENS_FILE")]
+    tokens_file: Option<PathBuf>,
+
+    /// Optional secret key for generating signed, expiring download URLs
     #[arg(long, env = "DOWNLOAD_SECRET")]
     download_secret: Option<String>,
+
+    /// Lifetime of signed download URLs, in seconds
+    #[arg(long, env = "DOWNLOAD_URL_TTL", default_value = "86400")]
+    download_url_ttl: u64,
+
+    /// Publish FairPlay-encrypted IPAs instead of leaving them out of repository.json
+    #[arg(long, env = "PUBLISH_ENCRYPTED")]
+    publish_encrypted: bool,
 }
 
 #[tokio::main]
@@ -71,13 +100,16 @@ async fn main() -> Result<()> {
     tracing::info!("  Listen URL: {}", args.listen_url);
     tracing::info!("  Listen Port: {}", args.listen_port);
     tracing::info!("  Apps Directory: {}", args.apps_dir.display());
-    if args.auth_token.is_some() {
+    if args.auth_token.is_some() || args.tokens_file.is_some() {
         tracing::info!("  Authentication: Enabled (token required as query parameter)");
     } else {
         tracing::info!("  Authentication: Disabled");
     }
     if args.download_secret.is_some() {
-        tracing::info!("  Download URLs: Obfuscated (using secret key)");
+        tracing::info!(
+            "  Download URLs: Signed (valid for {} seconds)",
+            args.download_url_ttl
+        );
     } else {
         tracing::info!("  Download URLs: Standard (non-obfuscated)");
     }
@@ -101,15 +133,64 @@ async fn main() -> Result<()> {
     }
     tracing::info!("config.json path: {}", config_json_path.display());
 
-    // Read and parse config.json
-    let config_content =
-        std::fs::read_to_string(&config_json_path).context("Failed to read config.json")?;
-    let config: models::Config =
-        serde_json::from_str(&config_content).context("Failed to parse config.json")?;
+    // Read, parse and validate config.json
+    let config = config::load_config(&config_json_path)?;
     tracing::info!("Loaded configuration for: {}", config.name);
 
-    // Discover IPAs (without cache for initial discovery)
-    let ipa_index = discover_ipas(&apps_dir, None)
+    let config = ConfigHandle::new(config);
+
+    // Reload config.json when it changes on disk or on SIGHUP
+    if let Err(err) = config::spawn_config_reloader(config_json_path.clone(), config.clone()) {
+        tracing::warn!(
+            "Failed to start config reloader, changes will require a restart: {:#}",
+            err
+        );
+    }
+
+    // Load named API tokens and reload them when the file changes or on SIGHUP
+    let token_store = match args.tokens_file {
+        Some(ref tokens_file) => {
+            let tokens_path = if tokens_file.is_absolute() {
+                tokens_file.clone()
+            } else {
+                base_path.join(tokens_file)
+            };
+            let store = token_store::load_token_store(&tokens_path)?;
+            tracing::info!(
+                "Loaded {} API tokens from {}",
+                store.tokens.len(),
+                tokens_path.display()
+            );
+
+            let handle = TokenStoreHandle::new(store);
+            if let Err(err) = token_store::spawn_token_store_reloader(tokens_path, handle.clone()) {
+                tracing::warn!(
+                    "Failed to start token store reloader, changes will require a restart: {:#}",
+                    err
+                );
+            }
+            Some(handle)
+        }
+        None => None,
+    };
+
+    // Create IPA metadata cache
+    let ipa_cache = Arc::new(IpaCache::new());
+
+    // Watch before the initial scan, so files changed while it runs are picked up afterwards
+    let watcher = match indexer::watch_apps_dir(&apps_dir) {
+        Ok(watcher) => Some(watcher),
+        Err(err) => {
+            tracing::warn!(
+                "Failed to start filesystem watcher, new IPAs will require a restart: {:#}",
+                err
+            );
+            None
+        }
+    };
+
+    // Build the initial index; the cache is shared with the background indexer
+    let ipa_index = discover_ipas(&apps_dir, Some(&ipa_cache))
         .await
         .context("Failed to discover IPAs")?;
 
@@ -117,24 +198,51 @@ async fn main() -> Result<()> {
         tracing::warn!("No IPAs discovered. Server will still run but no apps are available.");
     }
 
-    // Create IPA metadata cache
-    let ipa_cache = Arc::new(IpaCache::new());
+    let download_secret = args.download_secret.map(Arc::new);
+    let app_files = discover_app_files(&apps_dir);
+    let ipa_index = IndexHandle::new(ipa_index, app_files, download_secret.clone());
+
+    let icon_cache = IconCache::default();
+
+    // Hash IPAs in the background rather than before the listener binds
+    indexer::spawn_hasher(ipa_cache.clone(), ipa_index.clone());
+
+    // Keep the index up to date from filesystem change notifications
+    if let Some(watcher) = watcher {
+        indexer::spawn_indexer(
+            apps_dir,
+            ipa_cache,
+            icon_cache.clone(),
+            ipa_index.clone(),
+            watcher,
+        );
+    }
 
     // Create shared application state
     let state = AppState {
-        config: Arc::new(config),
+        config,
         base_path: base_path.clone(),
-        apps_dir,
         auth_token: args.auth_token,
-        download_secret: args.download_secret.map(Arc::new),
-        ipa_cache,
+        token_store,
+        download_secret,
+        download_url_ttl: Duration::from_secs(args.download_url_ttl),
+        publish_encrypted: args.publish_encrypted,
+        ipa_index,
+        icon_cache,
+        merged_config: MergedConfigCache::default(),
+        catalog: CatalogCache::default(),
     };
 
     // Configure CORS (allow all origins for AltStore compatibility)
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods([Method::GET, Method::HEAD])
-        .allow_headers([header::CONTENT_TYPE, header::ACCEPT]);
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::ACCEPT,
+            header::RANGE,
+            header::IF_RANGE,
+        ]);
 
     // Build the router
     let app = Router::new()
@@ -142,6 +250,9 @@ async fn main() -> Result<()> {
         .route("/repository.json", get(routes::serve_repository_json))
         .route("/apps/:app_name/:filename", get(routes::serve_ipa))
         .route("/download/:token", get(routes::serve_ipa_obfuscated))
+        .route("/icons/:file", get(routes::serve_icon))
+        .route("/diagnostics.json", get(routes::serve_diagnostics))
+        .route("/inspect.json", get(routes::serve_inspection))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth::validate_token,
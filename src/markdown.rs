@@ -0,0 +1,66 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+static HEADING: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s{0,3}#{1,6}\s+").unwrap());
+static LIST_ITEM: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(\s*)[-*+]\s+").unwrap());
+static IMAGE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"!\[([^\]]*)\]\([^)]*\)").unwrap());
+static LINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[([^\]]+)\]\(([^)]+)\)").unwrap());
+static EMPHASIS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\*\*|__)(.+?)(\*\*|__)|\*([^*\s][^*]*)\*|`([^`]+)`").unwrap());
+
+/// Converts Markdown release notes to the plain text AltStore displays
+/// Headings and emphasis lose their markers, list items become bullets and links
+/// keep their target in parentheses. Code fences are dropped, their contents kept.
+pub fn to_plain_text(markdown: &str) -> String {
+    let mut lines = Vec::new();
+
+    for line in markdown.lines() {
+        if line.trim_start().starts_with("```") {
+            continue;
+        }
+
+        let line = HEADING.replace(line, "");
+        let line = LIST_ITEM.replace(&line, "$1• ");
+        let line = IMAGE.replace_all(&line, "$1");
+        let line = LINK.replace_all(&line, |caps: &regex::Captures| {
+            if caps[1] == caps[2] {
+                caps[2].to_string()
+            } else {
+                format!("{} ({})", &caps[1], &caps[2])
+            }
+        });
+        let line = EMPHASIS.replace_all(&line, "$2$4$5");
+
+        // Collapse runs of blank lines into one
+        let line = line.trim_end().to_string();
+        if line.is_empty() && lines.last().is_none_or(|last: &String| last.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_plain_text() {
+        let markdown = "## What's new\n\n\n\
+            - **Faster** startup\n\
+            - Fixed `Settings` crash, see [#12](https://example.com/12)\n  \
+            * nested *item*\n\n\
+            ```\nlog output\n```\n";
+
+        assert_eq!(
+            to_plain_text(markdown),
+            "What's new\n\n\
+            • Faster startup\n\
+            • Fixed Settings crash, see #12 (https://example.com/12)\n  \
+            • nested item\n\n\
+            log output"
+        );
+    }
+}
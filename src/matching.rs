@@ -0,0 +1,229 @@
+use crate::discovery::{IpaEntry, IpaIndex};
+use crate::models::AppConfig;
+use std::collections::HashSet;
+
+/// Decides which configured app each discovered IPA belongs to
+///
+/// - An app with an explicit `directory` owns every IPA in that directory.
+/// - Otherwise IPAs are matched on CFBundleIdentifier, wherever they are stored.
+/// - IPAs without a readable bundle identifier, and apps whose bundle identifier is
+///   shared with another app (e.g. two tweaks of the same app), fall back to the
+///   directory named after the app.
+///
+/// An IPA filed under an app's directory whose bundle identifier belongs to no app
+/// is a mismatch: most likely it was put in the wrong folder.
+pub struct AppMatcher<'a> {
+    apps: &'a [AppConfig],
+    /// Bundle identifiers used by more than one app, which can't tell apps apart
+    shared_bundle_ids: HashSet<&'a str>,
+}
+
+/// How an IPA relates to an app
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Match {
+    /// The IPA belongs to the app
+    Exact,
+    /// The IPA is in the app's directory but has another bundle identifier
+    Mismatch,
+}
+
+impl<'a> AppMatcher<'a> {
+    pub fn new(apps: &'a [AppConfig]) -> Self {
+        let mut seen = HashSet::new();
+        let shared_bundle_ids = apps
+            .iter()
+            .map(|app| app.bundle_identifier.as_str())
+            .filter(|bundle_id| !seen.insert(*bundle_id))
+            .collect();
+
+        Self {
+            apps,
+            shared_bundle_ids,
+        }
+    }
+
+    /// Classifies an IPA against an app, None if it has nothing to do with it
+    pub fn classify(&self, app: &AppConfig, ipa: &IpaEntry) -> Option<Match> {
+        let in_app_directory = ipa.app_name == app.directory.as_deref().unwrap_or(&app.name);
+        let same_bundle_id = ipa
+            .bundle_identifier
+            .as_ref()
+            .is_none_or(|bundle_id| *bundle_id == app.bundle_identifier);
+
+        if app.directory.is_some() {
+            return match (in_app_directory, same_bundle_id) {
+                (false, _) => None,
+                (true, true) => Some(Match::Exact),
+                (true, false) => Some(Match::Mismatch),
+            };
+        }
+
+        match ipa.bundle_identifier {
+            Some(ref bundle_id) if !self.shared_bundle_ids.contains(bundle_id.as_str()) => {
+                if same_bundle_id && !self.claimed_by_directory(ipa) {
+                    Some(Match::Exact)
+                } else if in_app_directory && !self.is_configured(bundle_id) {
+                    Some(Match::Mismatch)
+                } else {
+                    None
+                }
+            }
+            _ if in_app_directory && same_bundle_id => Some(Match::Exact),
+            _ if in_app_directory => Some(Match::Mismatch),
+            _ => None,
+        }
+    }
+
+    /// Lists the IPAs filed under an app, across all directories, with how they match
+    pub fn ipas_for<'i>(&self, app: &AppConfig, index: &'i IpaIndex) -> Vec<(&'i IpaEntry, Match)> {
+        index
+            .values()
+            .flatten()
+            .filter_map(|ipa| Some((ipa, self.classify(app, ipa)?)))
+            .collect()
+    }
+
+    /// Finds the app an IPA is filed under, if any, preferring an exact match
+    pub fn app_for(&self, ipa: &IpaEntry) -> Option<(&'a AppConfig, Match)> {
+        let mut candidates = self
+            .apps
+            .iter()
+            .filter_map(|app| Some((app, self.classify(app, ipa)?)));
+        let first = candidates.next()?;
+
+        if first.1 == Match::Exact {
+            return Some(first);
+        }
+        Some(
+            candidates
+                .find(|(_, matched)| *matched == Match::Exact)
+                .unwrap_or(first),
+        )
+    }
+
+    /// An explicit `directory` takes the IPAs in it away from bundle identifier matching
+    fn claimed_by_directory(&self, ipa: &IpaEntry) -> bool {
+        self.apps
+            .iter()
+            .any(|app| app.directory.as_deref() == Some(ipa.app_name.as_str()))
+    }
+
+    fn is_configured(&self, bundle_id: &str) -> bool {
+        self.apps
+            .iter()
+            .any(|app| app.bundle_identifier == bundle_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn app(name: &str, bundle_identifier: &str, directory: Option<&str>) -> AppConfig {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "bundleIdentifier": bundle_identifier,
+            "directory": directory,
+            "developerName": "Dev",
+            "localizedDescription": "",
+            "iconURL": "",
+            "tintColor": "ffffff",
+            "category": "utilities",
+            "screenshotURLs": [],
+            "appPermissions": { "entitlements": [], "privacy": {} }
+        }))
+        .unwrap()
+    }
+
+    fn ipa(directory: &str, filename: &str, bundle_identifier: Option<&str>) -> IpaEntry {
+        IpaEntry {
+            app_name: directory.to_string(),
+            filename: filename.to_string(),
+            path: PathBuf::from(format!("/apps/{}/{}", directory, filename)),
+            release_date: "2025-01-13T00:00:00Z".to_string(),
+            bundle_identifier: bundle_identifier.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_matches_by_bundle_identifier_in_any_directory() {
+        let apps = vec![app("Renamed App", "com.example.app", None)];
+        let matcher = AppMatcher::new(&apps);
+
+        assert_eq!(
+            matcher.classify(&apps[0], &ipa("Old Name", "a.ipa", Some("com.example.app"))),
+            Some(Match::Exact)
+        );
+        assert_ne!(
+            matcher.classify(&apps[0], &ipa("Renamed App", "b.ipa", Some("com.other"))),
+            Some(Match::Exact)
+        );
+        // Without a bundle identifier the directory name decides
+        assert_eq!(
+            matcher.classify(&apps[0], &ipa("Renamed App", "c.ipa", None)),
+            Some(Match::Exact)
+        );
+    }
+
+    #[test]
+    fn test_shared_bundle_identifier_falls_back_to_directory() {
+        let apps = vec![
+            app("YTLite", "com.google.ios.youtube", None),
+            app("uYouPlus", "com.google.ios.youtube", None),
+        ];
+        let matcher = AppMatcher::new(&apps);
+        let ytlite = ipa("YTLite", "a.ipa", Some("com.google.ios.youtube"));
+
+        assert_eq!(matcher.classify(&apps[0], &ytlite), Some(Match::Exact));
+        assert_ne!(matcher.classify(&apps[1], &ytlite), Some(Match::Exact));
+        assert_eq!(
+            matcher.app_for(&ytlite).map(|(a, _)| a.name.as_str()),
+            Some("YTLite")
+        );
+    }
+
+    #[test]
+    fn test_unknown_bundle_identifier_in_app_directory_is_a_mismatch() {
+        let apps = vec![
+            app("App", "com.example.app", None),
+            app("Other", "com.example.other", None),
+        ];
+        let matcher = AppMatcher::new(&apps);
+
+        let stray = ipa("App", "a.ipa", Some("com.example.unknown"));
+        assert_eq!(matcher.classify(&apps[0], &stray), Some(Match::Mismatch));
+
+        // An IPA of another configured app is simply routed to that app
+        let misfiled = ipa("App", "b.ipa", Some("com.example.other"));
+        assert_eq!(matcher.classify(&apps[0], &misfiled), None);
+        assert_eq!(
+            matcher
+                .app_for(&misfiled)
+                .map(|(a, m)| (a.name.as_str(), m)),
+            Some(("Other", Match::Exact))
+        );
+    }
+
+    #[test]
+    fn test_explicit_directory() {
+        let apps = vec![
+            app("Pinned", "com.example.pinned", Some("builds")),
+            app("Other", "com.example.other", None),
+        ];
+        let matcher = AppMatcher::new(&apps);
+
+        // The pinned app owns its directory, whatever the IPAs inside are
+        let stray = ipa("builds", "a.ipa", Some("com.example.other"));
+        assert_eq!(matcher.classify(&apps[0], &stray), Some(Match::Mismatch));
+        assert_ne!(matcher.classify(&apps[1], &stray), Some(Match::Exact));
+        assert_eq!(
+            matcher.classify(
+                &apps[0],
+                &ipa("Pinned", "b.ipa", Some("com.example.pinned"))
+            ),
+            None
+        );
+    }
+}
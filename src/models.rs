@@ -1,3 +1,5 @@
+use crate::cache::CacheKey;
+use crate::pattern::FilenamePattern;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -20,6 +22,54 @@ pub struct Config {
     pub news: Vec<NewsItem>,
     #[serde(default, rename = "userInfo")]
     pub user_info: HashMap<String, serde_json::Value>,
+    /// Filename patterns tried for every app after the app's own (not published)
+    #[serde(default, rename = "filenamePatterns", skip_serializing)]
+    pub filename_patterns: Vec<FilenamePattern>,
+    /// When set, IPAs that match no app are published under a synthesized entry (not published)
+    #[serde(default, rename = "autoPublish", skip_serializing)]
+    pub auto_publish: Option<AutoPublish>,
+    /// What to do with IPAs in an app's directory that carry another bundle identifier (not published)
+    #[serde(default, rename = "bundleIdMismatch", skip_serializing)]
+    pub bundle_id_mismatch: MismatchPolicy,
+    /// What to do when several IPAs of an app resolve to the same version (not published)
+    #[serde(default, rename = "duplicateVersions", skip_serializing)]
+    pub duplicate_versions: DuplicatePolicy,
+}
+
+/// Handling of IPAs whose bundle identifier differs from the app they are filed under
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MismatchPolicy {
+    /// Publish the IPA and log a warning
+    #[default]
+    Warn,
+    /// Leave the IPA out of repository.json
+    Exclude,
+    /// Publish the IPA without a warning
+    Publish,
+}
+
+/// Handling of IPAs of one app that resolve to the same version, build and tweak version
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicatePolicy {
+    /// Publish the most recently modified IPA and drop the others
+    #[default]
+    Newest,
+    /// Publish every IPA, suffixing the older ones' versions with -1, -2, ...
+    Disambiguate,
+    /// Refuse to generate repository.json
+    Fail,
+}
+
+/// Defaults for app entries synthesized from unmatched IPAs
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AutoPublish {
+    /// AltStore category, "other" if unset
+    pub category: Option<String>,
+    /// Tint color, the repository's if unset
+    #[serde(rename = "tintColor")]
+    pub tint_color: Option<String>,
 }
 
 /// App configuration (base metadata without versions)
@@ -46,6 +96,30 @@ pub struct AppConfig {
     /// Versions can be manually configured or will be populated dynamically from filesystem
     #[serde(default)]
     pub versions: Vec<AppVersion>,
+    /// Filename patterns for this app's IPAs, tried before the global ones (not published)
+    #[serde(default, rename = "filenamePatterns", skip_serializing)]
+    pub filename_patterns: Vec<FilenamePattern>,
+    /// Directory under the apps directory holding this app's IPAs (not published)
+    /// When unset, IPAs are matched by bundle identifier, then by a directory named like the app
+    #[serde(default, skip_serializing)]
+    pub directory: Option<String>,
+    /// How the versions list is ordered, newest first (not published)
+    #[serde(default, rename = "versionOrder", skip_serializing)]
+    pub version_order: VersionOrder,
+    /// App directory serving the icon when iconURL is empty, found during generation
+    #[serde(skip)]
+    pub icon_directory: Option<String>,
+}
+
+/// Ordering of an app's versions; AltStore treats the first one as the current release
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionOrder {
+    /// By version, then build number and tweak version, then date
+    #[default]
+    Semantic,
+    /// By date, ties broken as for semantic ordering
+    Date,
 }
 
 /// App permissions structure
@@ -56,15 +130,81 @@ pub struct AppPermissions {
 }
 
 /// App version entry
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AppVersion {
     pub version: String,
+    /// Build number (CFBundleVersion); AltStore uses version + buildVersion to tell updates apart
+    #[serde(
+        default,
+        rename = "buildVersion",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub build_version: Option<String>,
+    /// Version of the injected tweak, read from the IPA filename (e.g. "5.2b1")
+    #[serde(
+        default,
+        rename = "tweakVersion",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub tweak_version: Option<String>,
+    /// Release channel captured by a filename pattern (e.g. "beta")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
     pub date: String,
     #[serde(rename = "localizedDescription")]
     pub localized_description: String,
     #[serde(rename = "downloadURL")]
     pub download_url: String,
     pub size: u64,
+    /// Hex-encoded SHA-256 of the IPA, lets AltStore verify the download
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Minimum iOS version required, extracted from MinimumOSVersion unless set manually
+    #[serde(
+        default,
+        rename = "minOSVersion",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub min_os_version: Option<String>,
+    /// Maximum supported iOS version (manual only)
+    #[serde(
+        default,
+        rename = "maxOSVersion",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_os_version: Option<String>,
+    /// Tweak dylibs and frameworks injected into the IPA, filled in from discovery
+    #[serde(
+        default,
+        rename = "injectedLibraries",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub injected_libraries: Vec<InjectedLibrary>,
+    /// Discovered IPA the entry downloads; its downloadURL is minted per request
+    #[serde(skip)]
+    pub ipa: Option<IpaSource>,
+}
+
+/// A discovered IPA, as the download routes address it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpaSource {
+    /// App directory holding the IPA
+    pub app_name: String,
+    pub filename: String,
+    /// Path and modification time, to look up the IPA's digest
+    pub key: CacheKey,
+}
+
+/// A non-Apple library loaded by an app's main executable
+///
+/// Nothing in an IPA tells injected tweaks apart from the app's own frameworks, so
+/// first-party libraries such as an @rpath framework of the developer are listed as well
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InjectedLibrary {
+    /// File name, e.g. "YTLite.dylib" or "Orion.framework"
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
 }
 
 /// News item for updates
@@ -0,0 +1,112 @@
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use std::str::FromStr;
+
+/// Named capture groups a filename pattern may use
+const GROUPS: &[&str] = &["app", "version", "build", "tweak", "channel"];
+
+/// A named-capture regex describing how versions are encoded in IPA filenames
+/// e.g. `(?P<app>.+)-v(?P<version>[\d.]+)\+(?P<tweak>.+)\.ipa`
+/// The pattern must match the whole filename and capture at least `version`.
+#[derive(Debug, Clone)]
+pub struct FilenamePattern(Regex);
+
+/// Values captured from a filename
+#[derive(Debug, Default, PartialEq)]
+pub struct FilenameCaptures {
+    pub version: String,
+    pub build: Option<String>,
+    pub tweak: Option<String>,
+    pub channel: Option<String>,
+}
+
+impl FilenamePattern {
+    /// Matches a filename, returning None if the pattern doesn't apply to it
+    pub fn captures(&self, filename: &str) -> Option<FilenameCaptures> {
+        let captures = self.0.captures(filename)?;
+        let group = |name: &str| {
+            captures
+                .name(name)
+                .map(|m| m.as_str().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        Some(FilenameCaptures {
+            version: group("version")?,
+            build: group("build"),
+            tweak: group("tweak"),
+            channel: group("channel"),
+        })
+    }
+}
+
+impl FromStr for FilenamePattern {
+    type Err = anyhow::Error;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        // Anchor the pattern so it describes the whole filename
+        let regex = Regex::new(&format!("^(?:{})$", pattern))
+            .map_err(|err| anyhow::anyhow!("Invalid filename pattern {}: {}", pattern, err))?;
+
+        let names: Vec<&str> = regex.capture_names().flatten().collect();
+        if !names.contains(&"version") {
+            anyhow::bail!("Filename pattern {} has no (?P<version>...) group", pattern);
+        }
+        if let Some(unknown) = names.iter().find(|name| !GROUPS.contains(name)) {
+            anyhow::bail!(
+                "Filename pattern {} uses unknown group {} (expected one of: {})",
+                pattern,
+                unknown,
+                GROUPS.join(", ")
+            );
+        }
+
+        Ok(Self(regex))
+    }
+}
+
+impl<'de> Deserialize<'de> for FilenamePattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        pattern.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_captures() {
+        let pattern: FilenamePattern = r"(?P<app>.+)-v(?P<version>[\d.]+)\+(?P<tweak>.+)\.ipa"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            pattern.captures("YouTube-v20.26.7+5.2b1.ipa"),
+            Some(FilenameCaptures {
+                version: "20.26.7".to_string(),
+                tweak: Some("5.2b1".to_string()),
+                ..Default::default()
+            })
+        );
+        assert_eq!(pattern.captures("YouTube_20.26.7.ipa"), None);
+    }
+
+    #[test]
+    fn test_pattern_matches_whole_filename() {
+        let pattern: FilenamePattern = r"(?P<version>[\d.]+)\.ipa".parse().unwrap();
+
+        assert!(pattern.captures("1.2.3.ipa").is_some());
+        assert!(pattern.captures("App_1.2.3.ipa").is_none());
+    }
+
+    #[test]
+    fn test_invalid_patterns_are_rejected() {
+        assert!("(?P<version>[".parse::<FilenamePattern>().is_err());
+        assert!(r"(?P<tweak>.+)\.ipa".parse::<FilenamePattern>().is_err());
+        assert!(r"(?P<version>.+)_(?P<flavor>.+)\.ipa"
+            .parse::<FilenamePattern>()
+            .is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// Subset of an embedded.mobileprovision profile we care about
+#[derive(Debug, Deserialize)]
+pub struct ProvisioningProfile {
+    /// Entitlements granted by the profile
+    #[serde(rename = "Entitlements", default)]
+    pub entitlements: plist::Dictionary,
+
+    /// Profile name as shown in the developer portal
+    #[serde(rename = "Name")]
+    pub name: Option<String>,
+
+    #[serde(rename = "TeamName")]
+    pub team_name: Option<String>,
+
+    /// Usually a single team ID
+    #[serde(rename = "TeamIdentifier", default)]
+    pub team_identifiers: Vec<String>,
+
+    #[serde(rename = "ExpirationDate")]
+    pub expiration_date: Option<plist::Date>,
+
+    /// UDIDs the profile is limited to (development and ad-hoc profiles)
+    #[serde(rename = "ProvisionedDevices")]
+    pub provisioned_devices: Option<Vec<String>>,
+
+    /// Set on enterprise (in-house) profiles
+    #[serde(rename = "ProvisionsAllDevices", default)]
+    pub provisions_all_devices: bool,
+}
+
+/// Kind of distribution a provisioning profile allows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProfileType {
+    Development,
+    AdHoc,
+    Enterprise,
+    AppStore,
+}
+
+/// Signing details of a pre-signed IPA, as read from its provisioning profile
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningInfo {
+    pub team_name: Option<String>,
+    pub team_id: Option<String>,
+    pub profile_name: Option<String>,
+    pub expiration_date: Option<DateTime<Utc>>,
+    pub profile_type: ProfileType,
+}
+
+impl ProvisioningProfile {
+    /// Classifies the profile the same way Xcode does
+    /// Device lists mark development and ad-hoc profiles; get-task-allow tells them apart
+    pub fn profile_type(&self) -> ProfileType {
+        if self.provisions_all_devices {
+            return ProfileType::Enterprise;
+        }
+
+        if self.provisioned_devices.is_some() {
+            let debuggable = self
+                .entitlements
+                .get("get-task-allow")
+                .and_then(plist::Value::as_boolean)
+                .unwrap_or(false);
+            return if debuggable {
+                ProfileType::Development
+            } else {
+                ProfileType::AdHoc
+            };
+        }
+
+        ProfileType::AppStore
+    }
+
+    /// Extracts the signing details worth surfacing
+    pub fn signing_info(&self) -> SigningInfo {
+        SigningInfo {
+            team_name: self.team_name.clone(),
+            team_id: self.team_identifiers.first().cloned(),
+            profile_name: self.name.clone(),
+            expiration_date: self
+                .expiration_date
+                .map(|date| DateTime::<Utc>::from(SystemTime::from(date))),
+            profile_type: self.profile_type(),
+        }
+    }
+}
+
+/// Parses an embedded.mobileprovision file
+/// The profile is a CMS (PKCS#7) envelope whose signed content is a plain XML plist,
+/// so the plist can be cut out without verifying the signature.
+pub fn parse_provisioning_profile(data: &[u8]) -> Result<ProvisioningProfile> {
+    let start = find(data, b"<?xml").context("No plist found in provisioning profile")?;
+    let end = find(&data[start..], b"</plist>")
+        .map(|end| start + end + b"</plist>".len())
+        .context("Unterminated plist in provisioning profile")?;
+
+    plist::from_bytes(&data[start..end]).context("Failed to parse provisioning profile plist")
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wraps a profile plist in bytes resembling a DER envelope
+    fn profile(body: &str) -> Vec<u8> {
+        let mut data = vec![0x30, 0x82, 0x1f, 0x00, 0x06, 0x09];
+        data.extend_from_slice(
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>{}</dict>
+</plist>"#,
+                body
+            )
+            .as_bytes(),
+        );
+        data.extend_from_slice(&[0xa0, 0x82, 0x0b, 0x00]);
+        data
+    }
+
+    #[test]
+    fn test_parse_profile_entitlements() {
+        let data = profile(
+            r#"<key>Entitlements</key>
+<dict>
+    <key>application-identifier</key>
+    <string>TEAMID.com.example.app</string>
+    <key>aps-environment</key>
+    <string>production</string>
+</dict>"#,
+        );
+
+        let profile = parse_provisioning_profile(&data).unwrap();
+        let keys: Vec<&String> = profile.entitlements.keys().collect();
+        assert_eq!(keys, vec!["application-identifier", "aps-environment"]);
+    }
+
+    #[test]
+    fn test_signing_info() {
+        let data = profile(
+            r#"<key>Name</key>
+<string>Example Ad Hoc</string>
+<key>TeamName</key>
+<string>Example Corp</string>
+<key>TeamIdentifier</key>
+<array><string>ABCDE12345</string></array>
+<key>ExpirationDate</key>
+<date>2026-05-01T12:00:00Z</date>
+<key>ProvisionedDevices</key>
+<array><string>00008030-001A</string></array>
+<key>Entitlements</key>
+<dict>
+    <key>get-task-allow</key>
+    <false/>
+</dict>"#,
+        );
+
+        let signing = parse_provisioning_profile(&data).unwrap().signing_info();
+        assert_eq!(signing.team_name.as_deref(), Some("Example Corp"));
+        assert_eq!(signing.team_id.as_deref(), Some("ABCDE12345"));
+        assert_eq!(signing.profile_name.as_deref(), Some("Example Ad Hoc"));
+        assert_eq!(
+            signing.expiration_date.map(|date| date.to_rfc3339()),
+            Some("2026-05-01T12:00:00+00:00".to_string())
+        );
+        assert_eq!(signing.profile_type, ProfileType::AdHoc);
+    }
+
+    #[test]
+    fn test_profile_type() {
+        let development = profile(
+            r#"<key>ProvisionedDevices</key>
+<array/>
+<key>Entitlements</key>
+<dict><key>get-task-allow</key><true/></dict>"#,
+        );
+        let enterprise = profile("<key>ProvisionsAllDevices</key><true/>");
+        let app_store = profile("<key>Name</key><string>Store</string>");
+
+        let profile_type = |data: &[u8]| parse_provisioning_profile(data).unwrap().profile_type();
+        assert_eq!(profile_type(&development), ProfileType::Development);
+        assert_eq!(profile_type(&enterprise), ProfileType::Enterprise);
+        assert_eq!(profile_type(&app_store), ProfileType::AppStore);
+    }
+
+    #[test]
+    fn test_profile_without_plist() {
+        assert!(parse_provisioning_profile(&[0x30, 0x82, 0x00]).is_err());
+    }
+}
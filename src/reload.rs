@@ -0,0 +1,118 @@
+use anyhow::{Context, Result};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, RwLock};
+
+/// Quiet period to wait for after a change to a watched file before reloading
+/// Editors often write a file in several steps (truncate, write, rename)
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Thread-safe handle to a value that is replaced as a whole on reload
+/// Readers get a cheap `Arc` clone; reloads swap in a whole new value
+pub struct Shared<T> {
+    inner: Arc<RwLock<Arc<T>>>,
+}
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Shared<T> {
+    /// Creates a handle holding the given initial value
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(Arc::new(value))),
+        }
+    }
+
+    /// Returns the current value
+    pub async fn snapshot(&self) -> Arc<T> {
+        self.inner.read().await.clone()
+    }
+
+    /// Atomically replaces the current value
+    pub async fn replace(&self, value: T) {
+        *self.inner.write().await = Arc::new(value);
+    }
+}
+
+/// Calls `reload` whenever the file at `path` changes on disk or the process receives SIGHUP
+pub fn spawn_file_reloader<F, Fut>(path: PathBuf, reload: F) -> Result<()>
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    let (tx, mut rx) = mpsc::unbounded_channel::<()>();
+
+    // Watch the parent directory rather than the file itself: editors and
+    // deployment tools usually replace files through a rename, which
+    // would silently detach a watch placed on the old inode
+    let dir = path
+        .parent()
+        .with_context(|| format!("{} has no parent directory", path.display()))?
+        .to_path_buf();
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("{} has no file name", path.display()))?
+        .to_os_string();
+
+    let watch_tx = tx.clone();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        let Ok(event) = res else {
+            return;
+        };
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        if event
+            .paths
+            .iter()
+            .any(|p| p.file_name() == Some(file_name.as_os_str()))
+        {
+            let _ = watch_tx.send(());
+        }
+    })
+    .context("Failed to create file watcher")?;
+
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .with_context(|| format!("Failed to watch {}", dir.display()))?;
+
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangup = signal(SignalKind::hangup()).context("Failed to listen for SIGHUP")?;
+        let signal_tx = tx;
+        let watched = path.display().to_string();
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                tracing::info!("Received SIGHUP, reloading {}", watched);
+                let _ = signal_tx.send(());
+            }
+        });
+    }
+
+    tracing::info!("Watching {} for changes", path.display());
+
+    tokio::spawn(async move {
+        // Keep the watcher alive for as long as the task runs
+        let _watcher = watcher;
+
+        while rx.recv().await.is_some() {
+            // Coalesce the burst of events that usually follows
+            while let Ok(Some(())) = tokio::time::timeout(DEBOUNCE, rx.recv()).await {}
+
+            reload().await;
+        }
+    });
+
+    Ok(())
+}
@@ -1,19 +1,29 @@
-use crate::discovery::{discover_ipas, is_valid_path_component};
+use crate::discovery::{is_valid_path_component, IpaEntry};
+use crate::matching::AppMatcher;
+use crate::models::Config;
 use crate::state::AppState;
-use crate::token::generate_download_token;
+use crate::token::{verify_download, DownloadUrlError};
+use crate::token_store::TokenGrant;
 use axum::{
     body::Body,
-    extract::{Path, State},
-    http::{header, StatusCode},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
     response::Response,
+    Extension,
 };
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::io::SeekFrom;
 use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use tokio_util::io::ReaderStream;
 
 /// Serves IPA files from the discovered index
 pub async fn serve_ipa(
     Path((app_name, filename)): Path<(String, String)>,
     State(state): State<AppState>,
+    grant: Option<Extension<TokenGrant>>,
+    headers: HeaderMap,
 ) -> Result<Response, (StatusCode, String)> {
     tracing::debug!("Request for IPA: {}/{}", app_name, filename);
 
@@ -49,19 +59,11 @@ pub async fn serve_ipa(
         ));
     }
 
-    // Re-discover IPAs to get current filesystem state (using cache)
-    let ipa_index = discover_ipas(&state.apps_dir, Some(&state.ipa_cache))
-        .await
-        .map_err(|err| {
-            tracing::error!("Failed to discover IPAs: {}", err);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to discover IPA files: {}", err),
-            )
-        })?;
+    // Read the current index snapshot (kept up to date by the background indexer)
+    let ipa_index = state.ipa_index.snapshot().await;
 
     // Look up the app in the index
-    let app_ipas = ipa_index.get(&app_name).ok_or_else(|| {
+    let app_ipas = ipa_index.ipas.get(&app_name).ok_or_else(|| {
         tracing::debug!("App not found: {}", app_name);
         (
             StatusCode::NOT_FOUND,
@@ -81,6 +83,11 @@ pub async fn serve_ipa(
             )
         })?;
 
+    // Scoped tokens may only download the apps they can see
+    if let Some(Extension(ref grant)) = grant {
+        ensure_allowed(&state, grant, ipa_entry).await?;
+    }
+
     tracing::info!(
         "Serving IPA: {}/{} ({} bytes)",
         app_name,
@@ -88,113 +95,547 @@ pub async fn serve_ipa(
         ipa_entry.size
     );
 
-    // Open the file for streaming
-    let file = File::open(&ipa_entry.path).await.map_err(|err| {
-        tracing::error!("Failed to open IPA file: {}", err);
+    stream_ipa(&state, ipa_entry, &headers).await
+}
+
+/// Signature parameters carried by a signed download URL
+#[derive(Deserialize)]
+pub struct SignedDownloadQuery {
+    #[serde(default)]
+    expires: Option<i64>,
+    #[serde(default)]
+    sig: Option<String>,
+    #[serde(default)]
+    holder: Option<String>,
+}
+
+/// Serves IPA files using signed, expiring download URLs
+/// This handler verifies the signature, then looks up the IPA the token refers to
+pub async fn serve_ipa_obfuscated(
+    Path(token): Path<String>,
+    State(state): State<AppState>,
+    Query(query): Query<SignedDownloadQuery>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    tracing::debug!("Request for IPA with token: {}", token);
+
+    // Signed downloads only exist when a secret is configured
+    let Some(secret) = state.download_secret.as_ref().map(|s| s.as_str()) else {
+        tracing::debug!("Signed download requested but DOWNLOAD_SECRET is not configured");
+        return Err((StatusCode::NOT_FOUND, "Download not found".to_string()));
+    };
+
+    // Verify the signature before touching the index
+    let verification = match (query.expires, query.sig.as_deref()) {
+        (Some(expires), Some(signature)) => verify_download(
+            &token,
+            expires,
+            query.holder.as_deref(),
+            signature,
+            secret,
+            Utc::now().timestamp(),
+        ),
+        _ => Err(DownloadUrlError::Unsigned),
+    };
+
+    if let Err(err) = verification {
+        tracing::warn!("Rejected download for token {}: {}", token, err);
+        let status = match err {
+            DownloadUrlError::Expired => StatusCode::GONE,
+            DownloadUrlError::Unsigned | DownloadUrlError::InvalidSignature => {
+                StatusCode::FORBIDDEN
+            }
+        };
+        return Err((status, format!("Invalid download URL: {}", err)));
+    }
+
+    // Read the current index snapshot (kept up to date by the background indexer)
+    let ipa_index = state.ipa_index.snapshot().await;
+
+    // Single hash probe into the token map built alongside the index
+    let Some(ipa) = ipa_index.find_by_token(&token) else {
+        tracing::debug!("No IPA found for token: {}", token);
+        return Err((StatusCode::NOT_FOUND, "Download not found".to_string()));
+    };
+
+    // URLs minted for a named token stay bound to that token's status and scope
+    if let Some(ref holder) = query.holder {
+        let Some(ref token_store) = state.token_store else {
+            tracing::warn!(
+                "Signed download for holder {} but no token store is configured",
+                holder
+            );
+            return Err((StatusCode::FORBIDDEN, "Download not allowed".to_string()));
+        };
+
+        let grant = token_store
+            .snapshot()
+            .await
+            .resolve_holder(holder, Utc::now())
+            .map_err(|rejection| {
+                tracing::warn!("Rejected signed download: {}", rejection);
+                (StatusCode::FORBIDDEN, "Download not allowed".to_string())
+            })?;
+
+        ensure_allowed(&state, &grant, ipa).await?;
+    }
+
+    tracing::info!(
+        "Serving IPA via signed URL: {}/{} ({} bytes)",
+        ipa.app_name,
+        ipa.filename,
+        ipa.size
+    );
+
+    stream_ipa(&state, ipa, &headers).await
+}
+
+/// Rejects the request if the token grant doesn't cover the app an IPA belongs to
+/// The app may be named in the scope by app name or bundle identifier
+pub(super) async fn ensure_allowed(
+    state: &AppState,
+    grant: &TokenGrant,
+    ipa: &IpaEntry,
+) -> Result<(), (StatusCode, String)> {
+    let ipa_index = state.ipa_index.snapshot().await;
+    if grant_covers(grant, &*state.published_apps(&ipa_index).await, ipa) {
+        return Ok(());
+    }
+
+    tracing::warn!(
+        "Token holder {} is not allowed to download {}/{}",
+        grant.holder.as_deref().unwrap_or("(legacy token)"),
+        ipa.app_name,
+        ipa.filename
+    );
+    Err((
+        StatusCode::FORBIDDEN,
+        "This token does not grant access to this app".to_string(),
+    ))
+}
+
+/// Checks whether a token grant covers the app an IPA belongs to
+/// Only the owning app counts, as in repository.json: the IPA's own bundle identifier may
+/// be shared by several apps, and IPAs that belong to no app are refused to scoped tokens.
+/// `config` must list the published apps, including the ones autoPublish synthesizes.
+pub(super) fn grant_covers(grant: &TokenGrant, config: &Config, ipa: &IpaEntry) -> bool {
+    let owner = AppMatcher::new(&config.apps)
+        .app_for(ipa)
+        .map(|(app, _)| app);
+
+    let identifiers = owner
+        .map(|app| [app.name.as_str(), app.bundle_identifier.as_str()])
+        .into_iter()
+        .flatten();
+
+    grant.allows(identifiers)
+}
+
+/// Byte range selected by a Range header
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    /// No usable Range header: send the whole file
+    Full,
+    /// Inclusive byte range within the file
+    Partial { start: u64, end: u64 },
+    /// Range lies entirely outside the file
+    Unsatisfiable,
+}
+
+/// Parses a Range header value against a file of the given size
+/// Only single ranges are honoured; malformed and multi-range requests fall
+/// back to the full file, which RFC 9110 allows a server to do
+fn parse_range(value: &str, size: u64) -> ByteRange {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return ByteRange::Full;
+    };
+
+    if spec.contains(',') {
+        tracing::debug!("Multi-range request ignored, sending full file: {}", value);
+        return ByteRange::Full;
+    }
+
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+
+    let (start, end) = match (start.trim(), end.trim()) {
+        // Suffix range: the last N bytes
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(len) => (size.saturating_sub(len), size.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        },
+        // Open-ended range: from start to end of file
+        (start, "") => match start.parse::<u64>() {
+            Ok(start) => (start, size.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        },
+        (start, end) => match (start.parse::<u64>(), end.parse::<u64>()) {
+            (Ok(start), Ok(end)) if start <= end => (start, end.min(size.saturating_sub(1))),
+            _ => return ByteRange::Full,
+        },
+    };
+
+    if size == 0 || start >= size {
+        return ByteRange::Unsatisfiable;
+    }
+
+    ByteRange::Partial { start, end }
+}
+
+/// Strong validator for an IPA, from its size and modification time
+/// The content hash is only known once background hashing gets to the file; a validator
+/// switching to it would break the If-Range of downloads started before
+fn entity_tag(ipa: &IpaEntry) -> String {
+    format!("\"{:x}-{:x}\"", ipa.size, ipa.mtime_secs)
+}
+
+/// Formats a hex SHA-256 as an RFC 3230 `Digest` header value
+fn digest_header(sha256: &str) -> Option<String> {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+
+    let bytes = (0..sha256.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(sha256.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    Some(format!("sha-256={}", STANDARD.encode(bytes)))
+}
+
+/// Formats the modification time as an HTTP date
+fn last_modified(ipa: &IpaEntry) -> Option<String> {
+    let datetime = DateTime::<Utc>::from_timestamp(ipa.mtime_secs as i64, 0)?;
+    Some(datetime.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+}
+
+/// Returns true if an If-None-Match header lists the given entity tag
+fn etag_matches(value: &str, etag: &str) -> bool {
+    value
+        .split(',')
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+/// Returns true if the If-Range precondition still holds for the current file
+/// Both entity tags and HTTP dates are accepted; entity tags must match exactly
+fn if_range_matches(value: &str, etag: &str, ipa: &IpaEntry) -> bool {
+    let value = value.trim();
+    if value.starts_with('"') || value.starts_with("W/") {
+        return value == etag;
+    }
+
+    DateTime::parse_from_rfc2822(value)
+        .map(|date| date.timestamp() == ipa.mtime_secs as i64)
+        .unwrap_or(false)
+}
+
+/// Streams an indexed IPA, honouring conditional and Range requests
+/// Shared by the direct and obfuscated download routes
+async fn stream_ipa(
+    state: &AppState,
+    ipa: &IpaEntry,
+    headers: &HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let etag = entity_tag(ipa);
+    let last_modified = last_modified(ipa);
+    // Only known once the background hasher got to the file
+    let digest = state
+        .ipa_index
+        .digest(&(ipa.path.clone(), ipa.mtime_secs))
+        .await;
+
+    let build_error = |err: axum::http::Error| {
+        tracing::error!("Failed to build response: {}", err);
         (
             StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to open file: {}", err),
+            format!("Failed to build response: {}", err),
         )
-    })?;
+    };
+
+    let mut builder = Response::builder()
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ETAG, &etag);
+    if let Some(ref last_modified) = last_modified {
+        builder = builder.header(header::LAST_MODIFIED, last_modified);
+    }
+    if let Some(digest) = digest.as_deref().and_then(digest_header) {
+        builder = builder.header("digest", digest);
+    }
 
-    // Create a stream from the file
-    let stream = ReaderStream::new(file);
-    let body = Body::from_stream(stream);
+    // The client already has this exact file
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| etag_matches(v, &etag));
+    if not_modified {
+        tracing::debug!("IPA not modified: {}", ipa.filename);
+        return builder
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .map_err(build_error);
+    }
+
+    // A Range only applies if the file hasn't changed since the client's partial copy
+    let range_still_valid = headers
+        .get(header::IF_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .is_none_or(|v| if_range_matches(v, &etag, ipa));
 
-    // Build response with proper headers
-    let response = Response::builder()
-        .status(StatusCode::OK)
+    let range = match headers.get(header::RANGE).and_then(|v| v.to_str().ok()) {
+        Some(value) if range_still_valid => parse_range(value, ipa.size),
+        _ => ByteRange::Full,
+    };
+
+    builder = builder
         .header(header::CONTENT_TYPE, "application/octet-stream")
-        .header(header::CONTENT_LENGTH, ipa_entry.size.to_string())
         .header(
             header::CONTENT_DISPOSITION,
-            format!("attachment; filename=\"{}\"", filename),
+            format!("attachment; filename=\"{}\"", ipa.filename),
+        );
+
+    let (start, end) = match range {
+        ByteRange::Full => (0, ipa.size.saturating_sub(1)),
+        ByteRange::Partial { start, end } => (start, end),
+        ByteRange::Unsatisfiable => {
+            tracing::debug!(
+                "Unsatisfiable range for {} ({} bytes)",
+                ipa.filename,
+                ipa.size
+            );
+            return builder
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", ipa.size))
+                .body(Body::empty())
+                .map_err(build_error);
+        }
+    };
+
+    // Open the file for streaming
+    let mut file = File::open(&ipa.path).await.map_err(|err| {
+        tracing::error!("Failed to open IPA file: {}", err);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to open file: {}", err),
         )
-        .body(body)
-        .map_err(|err| {
-            tracing::error!("Failed to build response: {}", err);
+    })?;
+
+    let response = if let ByteRange::Partial { .. } = range {
+        let length = end - start + 1;
+        tracing::debug!(
+            "Serving bytes {}-{}/{} of {}",
+            start,
+            end,
+            ipa.size,
+            ipa.filename
+        );
+
+        file.seek(SeekFrom::Start(start)).await.map_err(|err| {
+            tracing::error!("Failed to seek IPA file: {}", err);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to build response: {}", err),
+                format!("Failed to read file: {}", err),
             )
         })?;
 
-    Ok(response)
+        builder
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(header::CONTENT_LENGTH, length.to_string())
+            .header(
+                header::CONTENT_RANGE,
+                format!("bytes {}-{}/{}", start, end, ipa.size),
+            )
+            .body(Body::from_stream(ReaderStream::new(file.take(length))))
+    } else {
+        builder
+            .status(StatusCode::OK)
+            .header(header::CONTENT_LENGTH, ipa.size.to_string())
+            .body(Body::from_stream(ReaderStream::new(file)))
+    };
+
+    response.map_err(build_error)
 }
 
-/// Serves IPA files using obfuscated download tokens
-/// This handler searches for the IPA that matches the provided token
-pub async fn serve_ipa_obfuscated(
-    Path(token): Path<String>,
-    State(state): State<AppState>,
-) -> Result<Response, (StatusCode, String)> {
-    tracing::debug!("Request for IPA with token: {}", token);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::IconCache;
+    use crate::config::{ConfigHandle, MergedConfigCache};
+    use crate::discovery::{AppFiles, IpaIndex};
+    use crate::generator::CatalogCache;
+    use crate::indexer::IndexHandle;
+    use std::path::PathBuf;
+    use std::time::Duration;
 
-    // Re-discover IPAs to get current filesystem state (using cache)
-    let ipa_index = discover_ipas(&state.apps_dir, Some(&state.ipa_cache))
-        .await
-        .map_err(|err| {
-            tracing::error!("Failed to discover IPAs: {}", err);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to discover IPA files: {}", err),
-            )
-        })?;
+    #[test]
+    fn test_grant_covers_only_the_owning_app() {
+        let app = |name: &str| {
+            serde_json::json!({
+                "name": name,
+                "bundleIdentifier": "com.google.ios.youtube",
+                "developerName": "Dev",
+                "localizedDescription": "",
+                "iconURL": "",
+                "tintColor": "ffffff",
+                "category": "utilities",
+                "screenshotURLs": [],
+                "appPermissions": { "entitlements": [], "privacy": {} }
+            })
+        };
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "name": "Repo",
+            "identifier": "com.example.repo",
+            "website": "https://example.com",
+            "tintColor": "ffffff",
+            "iconURL": "",
+            "sourceURL": "",
+            "apps": [app("YTLite"), app("uYouPlus")]
+        }))
+        .unwrap();
+        let ipa = |directory: &str| IpaEntry {
+            app_name: directory.to_string(),
+            filename: "YouTube.ipa".to_string(),
+            bundle_identifier: Some("com.google.ios.youtube".to_string()),
+            ..Default::default()
+        };
+        let grant = TokenGrant {
+            holder: Some("alice".to_string()),
+            apps: Some(vec!["YTLite".to_string()]),
+        };
+
+        assert!(grant_covers(&grant, &config, &ipa("YTLite")));
+        assert!(!grant_covers(&grant, &config, &ipa("uYouPlus")));
+        // Directory names and bundle identifiers of IPAs no app owns don't count
+        assert!(!grant_covers(&grant, &config, &ipa("Unlisted")));
+        assert!(grant_covers(
+            &TokenGrant::unrestricted(),
+            &config,
+            &ipa("Unlisted")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_scoped_token_downloads_auto_published_ipa() {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "name": "Repo",
+            "identifier": "com.example.repo",
+            "website": "https://example.com",
+            "tintColor": "ffffff",
+            "iconURL": "",
+            "sourceURL": "",
+            "apps": [],
+            "autoPublish": {}
+        }))
+        .unwrap();
+        let ipa = IpaEntry {
+            app_name: "NewApp".to_string(),
+            filename: "NewApp_1.0.ipa".to_string(),
+            bundle_identifier: Some("com.example.new".to_string()),
+            bundle_version: Some("1".to_string()),
+            bundle_name: Some("New App".to_string()),
+            ..Default::default()
+        };
+        let mut index = IpaIndex::new();
+        index.insert("NewApp".to_string(), vec![ipa.clone()]);
+
+        let state = AppState {
+            config: ConfigHandle::new(config),
+            base_path: PathBuf::new(),
+            auth_token: None,
+            token_store: None,
+            download_secret: None,
+            download_url_ttl: Duration::from_secs(60),
+            publish_encrypted: false,
+            ipa_index: IndexHandle::new(index, AppFiles::new(), None),
+            icon_cache: IconCache::default(),
+            merged_config: MergedConfigCache::default(),
+            catalog: CatalogCache::default(),
+        };
+        let grant = |app: &str| TokenGrant {
+            holder: Some("alice".to_string()),
+            apps: Some(vec![app.to_string()]),
+        };
 
-    // Get the secret if configured
-    let secret = state.download_secret.as_ref().map(|s| s.as_str());
-
-    // Search through all apps and IPAs to find the one matching this token
-    for (app_name, ipas) in ipa_index.iter() {
-        for ipa in ipas {
-            let ipa_token = generate_download_token(app_name, &ipa.filename, secret);
-
-            if ipa_token == token {
-                // Found the matching IPA!
-                tracing::info!(
-                    "Serving IPA via obfuscated URL: {}/{} ({} bytes)",
-                    app_name,
-                    ipa.filename,
-                    ipa.size
-                );
-
-                // Open the file for streaming
-                let file = File::open(&ipa.path).await.map_err(|err| {
-                    tracing::error!("Failed to open IPA file: {}", err);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        format!("Failed to open file: {}", err),
-                    )
-                })?;
-
-                // Create a stream from the file
-                let stream = ReaderStream::new(file);
-                let body = Body::from_stream(stream);
-
-                // Build response with proper headers
-                let response = Response::builder()
-                    .status(StatusCode::OK)
-                    .header(header::CONTENT_TYPE, "application/octet-stream")
-                    .header(header::CONTENT_LENGTH, ipa.size.to_string())
-                    .header(
-                        header::CONTENT_DISPOSITION,
-                        format!("attachment; filename=\"{}\"", ipa.filename),
-                    )
-                    .body(body)
-                    .map_err(|err| {
-                        tracing::error!("Failed to build response: {}", err);
-                        (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            format!("Failed to build response: {}", err),
-                        )
-                    })?;
-
-                return Ok(response);
+        // The synthesized app is published as "New App", so the token may download its IPAs
+        assert!(ensure_allowed(&state, &grant("New App"), &ipa)
+            .await
+            .is_ok());
+        assert!(ensure_allowed(&state, &grant("com.example.new"), &ipa)
+            .await
+            .is_ok());
+        assert!(ensure_allowed(&state, &grant("Other"), &ipa).await.is_err());
+    }
+
+    #[test]
+    fn test_parse_range_single() {
+        assert_eq!(
+            parse_range("bytes=0-99", 1000),
+            ByteRange::Partial { start: 0, end: 99 }
+        );
+        assert_eq!(
+            parse_range("bytes=500-", 1000),
+            ByteRange::Partial {
+                start: 500,
+                end: 999
             }
-        }
+        );
+        assert_eq!(
+            parse_range("bytes=-100", 1000),
+            ByteRange::Partial {
+                start: 900,
+                end: 999
+            }
+        );
+        // End past the file is clamped
+        assert_eq!(
+            parse_range("bytes=900-5000", 1000),
+            ByteRange::Partial {
+                start: 900,
+                end: 999
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_range_unsatisfiable() {
+        assert_eq!(parse_range("bytes=1000-", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 1000), ByteRange::Unsatisfiable);
     }
 
-    // No matching token found
-    tracing::debug!("No IPA found for token: {}", token);
-    Err((StatusCode::NOT_FOUND, "Download not found".to_string()))
+    #[test]
+    fn test_parse_range_ignored() {
+        assert_eq!(parse_range("bytes=0-1,5-9", 1000), ByteRange::Full);
+        assert_eq!(parse_range("items=0-1", 1000), ByteRange::Full);
+        assert_eq!(parse_range("bytes=9-1", 1000), ByteRange::Full);
+        assert_eq!(parse_range("bytes=abc", 1000), ByteRange::Full);
+    }
+
+    #[test]
+    fn test_digest_header() {
+        // SHA-256 of the empty string
+        let sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        assert_eq!(
+            digest_header(sha256).as_deref(),
+            Some("sha-256=47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=")
+        );
+        assert_eq!(digest_header("xyz"), None);
+    }
+
+    #[test]
+    fn test_entity_tag() {
+        let ipa = IpaEntry {
+            size: 0x1000,
+            mtime_secs: 0x20,
+            ..Default::default()
+        };
+        assert_eq!(entity_tag(&ipa), "\"1000-20\"");
+    }
+
+    #[test]
+    fn test_etag_matches() {
+        assert!(etag_matches("\"a-b\"", "\"a-b\""));
+        assert!(etag_matches("\"x\", W/\"a-b\"", "\"a-b\""));
+        assert!(etag_matches("*", "\"a-b\""));
+        assert!(!etag_matches("\"x\"", "\"a-b\""));
+    }
 }
@@ -0,0 +1,35 @@
+use crate::diagnostics::{Diagnostics, IssueKind};
+use crate::routes::apps::grant_covers;
+use crate::state::AppState;
+use crate::token_store::TokenGrant;
+use axum::{extract::State, Extension, Json};
+
+/// Reports problems with the discovered IPAs, such as FairPlay-encrypted binaries
+/// or bundle identifiers that don't match the app they are filed under, and app.json
+/// files that couldn't be applied
+/// Scoped tokens only see the issues of the apps they can see
+pub async fn serve_diagnostics(
+    State(state): State<AppState>,
+    grant: Option<Extension<TokenGrant>>,
+) -> Json<Diagnostics> {
+    let ipa_index = state.ipa_index.snapshot().await;
+    let merged = state.merged_config(&ipa_index).await;
+    let mut diagnostics =
+        Diagnostics::collect(&merged.config, &ipa_index.ipas, &merged.app_file_errors);
+
+    if let Some(Extension(ref grant)) = grant {
+        let published = state.published_apps(&ipa_index).await;
+        diagnostics.issues.retain(|issue| {
+            if issue.kind == IssueKind::InvalidAppFile {
+                return grant.allows([issue.app_name.as_str()]);
+            }
+            ipa_index
+                .ipas
+                .get(&issue.app_name)
+                .and_then(|ipas| ipas.iter().find(|ipa| ipa.filename == issue.filename))
+                .is_some_and(|ipa| grant_covers(grant, &published, ipa))
+        });
+    }
+
+    Json(diagnostics)
+}
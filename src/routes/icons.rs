@@ -0,0 +1,110 @@
+use crate::discovery::is_valid_path_component;
+use crate::icon::normalize_png;
+use crate::ipa_info::read_archive_entry;
+use crate::routes::apps::ensure_allowed;
+use crate::state::AppState;
+use crate::token_store::TokenGrant;
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+
+/// Serves the primary icon extracted from an app's most recent IPA
+/// The icon is read from the archive on demand and converted from Apple's CgBI format
+pub async fn serve_icon(
+    Path(file): Path<String>,
+    State(state): State<AppState>,
+    grant: Option<Extension<TokenGrant>>,
+) -> Result<Response, (StatusCode, String)> {
+    tracing::debug!("Request for icon: {}", file);
+
+    let Some(app_name) = file.strip_suffix(".png") else {
+        return Err((StatusCode::NOT_FOUND, "Icon not found".to_string()));
+    };
+
+    // Validate path component to prevent directory traversal
+    if !is_valid_path_component(app_name) {
+        tracing::warn!("Invalid app_name: {}", app_name);
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Invalid app name: {}", app_name),
+        ));
+    }
+
+    // Read the current index snapshot (kept up to date by the background indexer)
+    let ipa_index = state.ipa_index.snapshot().await;
+
+    // Use the newest IPA that has an icon
+    let ipa = ipa_index
+        .ipas
+        .get(app_name)
+        .and_then(|ipas| {
+            ipas.iter()
+                .filter(|ipa| ipa.icon_path.is_some())
+                .max_by_key(|ipa| ipa.mtime_secs)
+        })
+        .ok_or_else(|| {
+            tracing::debug!("No icon found for app: {}", app_name);
+            (
+                StatusCode::NOT_FOUND,
+                format!("No icon found for app: {}", app_name),
+            )
+        })?;
+
+    // Scoped tokens may only see the icons of the apps they can see
+    if let Some(Extension(ref grant)) = grant {
+        ensure_allowed(&state, grant, ipa).await?;
+    }
+
+    // Icons only change when the newest IPA does
+    let key = (ipa.path.clone(), ipa.mtime_secs);
+    let png = match state.icon_cache.get(app_name, &key).await {
+        Some(png) => png,
+        None => {
+            let ipa_path = ipa.path.clone();
+            let icon_path = ipa.icon_path.clone().unwrap_or_default();
+
+            // Unzipping and PNG conversion are blocking work
+            let png = tokio::task::spawn_blocking(move || {
+                read_archive_entry(&ipa_path, &icon_path).and_then(|data| normalize_png(&data))
+            })
+            .await
+            .map_err(|err| err.to_string())
+            .and_then(|result| result.map_err(|err| format!("{:#}", err)))
+            .map_err(|err| {
+                tracing::error!("Failed to extract icon for {}: {}", app_name, err);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to extract icon: {}", err),
+                )
+            })?;
+
+            let png = Bytes::from(png);
+            state
+                .icon_cache
+                .insert(app_name.to_string(), key, png.clone())
+                .await;
+            png
+        }
+    };
+
+    // Shared caches must not keep icons that only some tokens may see
+    let cache_control = if state.auth_token.is_some() || state.token_store.is_some() {
+        "private, max-age=3600"
+    } else {
+        "public, max-age=3600"
+    };
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "image/png"),
+            (header::CACHE_CONTROL, cache_control),
+        ],
+        png,
+    )
+        .into_response())
+}
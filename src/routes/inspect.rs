@@ -0,0 +1,54 @@
+use crate::provision::SigningInfo;
+use crate::routes::apps::grant_covers;
+use crate::state::AppState;
+use crate::token_store::TokenGrant;
+use axum::{extract::State, Extension, Json};
+use serde::Serialize;
+
+/// What the server knows about a single discovered IPA
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IpaInspection {
+    app_name: String,
+    filename: String,
+    bundle_identifier: Option<String>,
+    version: Option<String>,
+    build_version: Option<String>,
+    encrypted: bool,
+    /// Present only for pre-signed IPAs that embed a provisioning profile
+    signing: Option<SigningInfo>,
+}
+
+/// Lists every discovered IPA with its bundle and signing details
+/// Shows at a glance which IPAs were pre-signed, by which team and until when
+pub async fn serve_inspection(
+    State(state): State<AppState>,
+    grant: Option<Extension<TokenGrant>>,
+) -> Json<Vec<IpaInspection>> {
+    let ipa_index = state.ipa_index.snapshot().await;
+    let published = state.published_apps(&ipa_index).await;
+
+    let mut ipas: Vec<IpaInspection> = ipa_index
+        .ipas
+        .values()
+        .flatten()
+        .filter(|ipa| {
+            grant
+                .as_ref()
+                .is_none_or(|Extension(grant)| grant_covers(grant, &published, ipa))
+        })
+        .map(|ipa| IpaInspection {
+            app_name: ipa.app_name.clone(),
+            filename: ipa.filename.clone(),
+            bundle_identifier: ipa.bundle_identifier.clone(),
+            version: ipa.bundle_short_version.clone(),
+            build_version: ipa.bundle_version.clone(),
+            encrypted: ipa.encrypted,
+            signing: ipa.signing.clone(),
+        })
+        .collect();
+
+    ipas.sort_by(|a, b| (&a.app_name, &a.filename).cmp(&(&b.app_name, &b.filename)));
+
+    Json(ipas)
+}
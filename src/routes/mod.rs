@@ -1,5 +1,11 @@
 pub mod apps;
+pub mod diagnostics;
+pub mod icons;
+pub mod inspect;
 pub mod repository;
 
 pub use apps::{serve_ipa, serve_ipa_obfuscated};
+pub use diagnostics::serve_diagnostics;
+pub use icons::serve_icon;
+pub use inspect::serve_inspection;
 pub use repository::serve_repository_json;
@@ -1,12 +1,15 @@
-use crate::discovery::discover_ipas;
-use crate::generator::generate_repository;
+use crate::generator::render_repository;
 use crate::state::AppState;
+use crate::token::UrlSigning;
+use crate::token_store::TokenGrant;
 use axum::{
     extract::{Query, State},
     http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
+    Extension,
 };
 use serde::Deserialize;
+use std::collections::HashSet;
 
 #[derive(Deserialize)]
 pub struct RepositoryQuery {
@@ -35,46 +38,61 @@ fn base_url_from_headers(headers: &HeaderMap) -> String {
 /// Dynamically generates and serves repository.json based on config.json and discovered IPAs
 pub async fn serve_repository_json(
     State(state): State<AppState>,
+    grant: Option<Extension<TokenGrant>>,
     headers: HeaderMap,
     Query(query): Query<RepositoryQuery>,
 ) -> Result<Response, (StatusCode, String)> {
     tracing::debug!("Generating repository.json dynamically");
 
-    // Re-discover IPAs to reflect current filesystem state
-    let ipa_index = discover_ipas(&state.apps_dir, Some(&state.ipa_cache))
-        .await
-        .map_err(|err| {
-            tracing::error!("Failed to discover IPAs: {}", err);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to discover IPA files: {}", err),
-            )
-        })?;
+    // Read the current index snapshot (kept up to date by the background indexer)
+    let ipa_index = state.ipa_index.snapshot().await;
 
-    // Clone the config to avoid holding the Arc lock
-    let config = (*state.config).clone();
+    // Matching IPAs to apps only happens when the config or the index changes
+    let catalog = state.catalog(&ipa_index).await.map_err(|err| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to generate repository manifest: {}", err),
+        )
+    })?;
 
-    // Get download secret if configured
-    let download_secret = state.download_secret.as_ref().map(|s| s.as_str());
+    // Mint fresh signed download URLs if a secret is configured
+    let expires = chrono::Utc::now().timestamp() + state.download_url_ttl.as_secs() as i64;
+    let signing = state.download_secret.as_ref().map(|secret| UrlSigning {
+        secret: secret.as_str(),
+        expires,
+        holder: grant
+            .as_ref()
+            .and_then(|Extension(grant)| grant.holder.as_deref()),
+    });
 
     // Derive base URL from request headers
     let base_url = base_url_from_headers(&headers);
 
-    // Generate the repository with populated versions from discovered IPAs
-    let repository = generate_repository(
-        config,
-        &ipa_index,
+    // Mint this request's download and icon URLs
+    let mut repository = render_repository(
+        &catalog,
+        &*state.ipa_index.digests().await,
         &base_url,
-        download_secret,
+        signing.as_ref(),
         query.token.as_deref(),
-    )
-    .map_err(|err| {
-        tracing::error!("Failed to generate repository: {}", err);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to generate repository manifest: {}", err),
-        )
-    })?;
+    );
+
+    // Only publish the apps (and their news) the presented token can see
+    // Filtering after generation also covers the entries synthesized by autoPublish
+    if let Some(Extension(ref grant)) = grant {
+        repository
+            .apps
+            .retain(|app| grant.allows([app.name.as_str(), app.bundle_identifier.as_str()]));
+
+        let visible: HashSet<&str> = repository
+            .apps
+            .iter()
+            .map(|app| app.bundle_identifier.as_str())
+            .collect();
+        repository
+            .news
+            .retain(|news| visible.contains(news.app_id.as_str()));
+    }
 
     // Serialize to JSON
     let content = serde_json::to_string_pretty(&repository).map_err(|err| {
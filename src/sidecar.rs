@@ -0,0 +1,167 @@
+use crate::markdown;
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Deserializer};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Optional per-version metadata stored next to an IPA
+/// `<filename>.json` (e.g. `App_1.2.ipa.json`) holds the fields below, and
+/// `<filename>.md` holds Markdown release notes used when the JSON has none.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct Sidecar {
+    /// Release date, overriding the one read from the IPA
+    #[serde(default, deserialize_with = "deserialize_date")]
+    pub date: Option<DateTime<Utc>>,
+    /// Release notes in Markdown, converted to plain text when loaded
+    #[serde(default, rename = "localizedDescription")]
+    pub localized_description: Option<String>,
+    /// Publishes the version on the "beta" channel
+    #[serde(default)]
+    pub beta: bool,
+    /// Overrides the MinimumOSVersion read from the IPA
+    #[serde(default, rename = "minOSVersion")]
+    pub min_os_version: Option<String>,
+    #[serde(default, rename = "maxOSVersion")]
+    pub max_os_version: Option<String>,
+    /// Leaves the version out of repository.json, e.g. for a yanked release
+    #[serde(default, alias = "yanked")]
+    pub hidden: bool,
+}
+
+impl Sidecar {
+    /// Path of a sidecar belonging to an IPA, e.g. "App_1.2.ipa.json" for extension "json"
+    pub fn path_for(ipa_path: &Path, extension: &str) -> PathBuf {
+        let mut path = ipa_path.as_os_str().to_owned();
+        path.push(".");
+        path.push(extension);
+        PathBuf::from(path)
+    }
+
+    /// Loads the sidecars of an IPA, None if it has none
+    pub fn load(ipa_path: &Path) -> Result<Option<Self>> {
+        let json = read_optional(&Self::path_for(ipa_path, "json"))?;
+        let notes = read_optional(&Self::path_for(ipa_path, "md"))?;
+        if json.is_none() && notes.is_none() {
+            return Ok(None);
+        }
+
+        let mut sidecar: Self = match json {
+            Some(contents) => serde_json::from_str(&contents).with_context(|| {
+                format!(
+                    "Failed to parse {}",
+                    Self::path_for(ipa_path, "json").display()
+                )
+            })?,
+            None => Self::default(),
+        };
+        sidecar.localized_description = sidecar
+            .localized_description
+            .or(notes)
+            .map(|notes| markdown::to_plain_text(&notes))
+            .filter(|notes| !notes.is_empty());
+
+        Ok(Some(sidecar))
+    }
+}
+
+/// Reads a file that may not exist
+fn read_optional(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// Parses an RFC 3339 timestamp, or a plain YYYY-MM-DD date taken as midnight UTC
+pub fn parse_date(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|datetime| datetime.and_utc())
+        .with_context(|| {
+            format!(
+                "Invalid date {}: expected an RFC 3339 timestamp or YYYY-MM-DD",
+                value
+            )
+        })
+}
+
+fn deserialize_date<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DateTime<Utc>>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| parse_date(&value).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_for() {
+        assert_eq!(
+            Sidecar::path_for(Path::new("/apps/App/App_1.2.ipa"), "json"),
+            PathBuf::from("/apps/App/App_1.2.ipa.json")
+        );
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = std::env::temp_dir().join(format!("sidecar-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let ipa = dir.join("App_1.2.ipa");
+
+        assert_eq!(Sidecar::load(&ipa).unwrap(), None);
+
+        fs::write(
+            dir.join("App_1.2.ipa.md"),
+            "## Changes\n\n- **Fixed** crash\n",
+        )
+        .unwrap();
+        let sidecar = Sidecar::load(&ipa).unwrap().unwrap();
+        assert_eq!(
+            sidecar.localized_description.as_deref(),
+            Some("Changes\n\n• Fixed crash")
+        );
+
+        // Notes in the JSON sidecar take precedence over the Markdown file
+        fs::write(
+            dir.join("App_1.2.ipa.json"),
+            r#"{"localizedDescription": "Hotfix", "yanked": true, "minOSVersion": "15.0"}"#,
+        )
+        .unwrap();
+        let sidecar = Sidecar::load(&ipa).unwrap().unwrap();
+        assert_eq!(sidecar.localized_description.as_deref(), Some("Hotfix"));
+        assert!(sidecar.hidden);
+        assert_eq!(sidecar.min_os_version.as_deref(), Some("15.0"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(
+            parse_date("2025-01-13T10:30:00+02:00")
+                .unwrap()
+                .to_rfc3339(),
+            "2025-01-13T08:30:00+00:00"
+        );
+        assert_eq!(
+            parse_date("2025-01-13").unwrap().to_rfc3339(),
+            "2025-01-13T00:00:00+00:00"
+        );
+        assert!(parse_date("13/01/2025").is_err());
+
+        let sidecar: Sidecar = serde_json::from_str(r#"{"date": "2025-01-13"}"#).unwrap();
+        assert!(sidecar.date.is_some());
+        assert!(serde_json::from_str::<Sidecar>(r#"{"date": "soon"}"#).is_err());
+    }
+}
@@ -1,18 +1,63 @@
-use crate::cache::IpaCache;
-use crate::models::Config;
+use crate::cache::IconCache;
+use crate::config::{ConfigHandle, MergedConfig, MergedConfigCache};
+use crate::generator::CatalogCache;
+use crate::indexer::{IndexHandle, IndexSnapshot};
+use crate::models::{Config, Repository};
+use crate::token_store::TokenStoreHandle;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 /// Shared application state
 #[derive(Clone)]
 pub struct AppState {
-    pub config: Arc<Config>,
+    /// Current config.json contents, reloaded when the file changes
+    pub config: ConfigHandle,
     #[allow(dead_code)]
     pub base_path: PathBuf,
-    pub apps_dir: PathBuf,
     pub auth_token: Option<String>,
-    /// Optional secret key for generating obfuscated download tokens
+    /// Optional named, scoped API tokens loaded from TOKENS_FILE
+    pub token_store: Option<TokenStoreHandle>,
+    /// Optional secret key for generating signed download URLs
     pub download_secret: Option<Arc<String>>,
-    /// Cache for IPA metadata to avoid repeated extraction
-    pub ipa_cache: Arc<IpaCache>,
+    /// How long signed download URLs stay valid after being minted
+    pub download_url_ttl: Duration,
+    /// Whether FairPlay-encrypted IPAs are published anyway
+    pub publish_encrypted: bool,
+    /// Current IPA index, kept up to date by the background indexer
+    pub ipa_index: IndexHandle,
+    /// Icons already extracted and normalized, so they aren't unzipped on every request
+    pub icon_cache: IconCache,
+    /// config.json merged with the app.json files of the current index
+    pub merged_config: MergedConfigCache,
+    /// Repository built from the merged config and the current index, before per-request URLs
+    pub catalog: CatalogCache,
+}
+
+impl AppState {
+    /// Returns the current config.json with the app.json files of an index snapshot applied
+    pub async fn merged_config(&self, ipa_index: &IndexSnapshot) -> Arc<MergedConfig> {
+        self.merged_config
+            .get(self.config.snapshot().await, ipa_index.app_files.clone())
+            .await
+    }
+
+    /// Returns the apps that own IPAs, as published in repository.json: the configured ones
+    /// plus those autoPublish synthesizes. Falls back to the configured apps if the catalog
+    /// can't be built
+    pub async fn published_apps(&self, ipa_index: &Arc<IndexSnapshot>) -> Arc<Config> {
+        match self.catalog(ipa_index).await {
+            Ok(catalog) => catalog,
+            Err(_) => Arc::new(self.merged_config(ipa_index).await.config.clone()),
+        }
+    }
+
+    /// Returns the catalog of the current config and an index snapshot
+    /// Fails if the IPAs can't be published as configured, e.g. duplicate versions
+    pub async fn catalog(&self, ipa_index: &Arc<IndexSnapshot>) -> Result<Arc<Repository>, String> {
+        let merged_config = self.merged_config(ipa_index).await;
+        self.catalog
+            .get(merged_config, ipa_index.clone(), self.publish_encrypted)
+            .await
+    }
 }
@@ -1,5 +1,48 @@
+use hmac::{Hmac, Mac};
 use sha2::{Digest, Sha256};
 
+type HmacSha256 = Hmac<Sha256>;
+
+/// Reasons a signed download URL is rejected
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum DownloadUrlError {
+    #[error("download URL is missing its signature")]
+    Unsigned,
+    #[error("download URL has expired")]
+    Expired,
+    #[error("download URL signature is invalid")]
+    InvalidSignature,
+}
+
+/// Parameters for minting signed, expiring download URLs
+#[derive(Debug, Clone, Copy)]
+pub struct UrlSigning<'a> {
+    /// Secret key used both for the file token and the HMAC
+    pub secret: &'a str,
+    /// Unix timestamp after which the URL stops working
+    pub expires: i64,
+    /// Optional identity of the token holder the URL was minted for
+    pub holder: Option<&'a str>,
+}
+
+impl UrlSigning<'_> {
+    /// Builds the path and query string of a signed download URL for an IPA
+    pub fn download_path(&self, app_name: &str, filename: &str) -> String {
+        let file_token = generate_download_token(app_name, filename, Some(self.secret));
+        let signature = sign_download(&file_token, self.expires, self.holder, self.secret);
+
+        let mut path = format!(
+            "/download/{}?expires={}&sig={}",
+            file_token, self.expires, signature
+        );
+        if let Some(holder) = self.holder {
+            path.push_str("&holder=");
+            path.push_str(&percent_encode(holder));
+        }
+        path
+    }
+}
+
 /// Generate a deterministic token for an IPA file
 /// The token is generated from a hash of app_name, filename, and an optional secret
 /// This ensures tokens remain consistent across server restarts
@@ -24,6 +67,70 @@ pub fn generate_download_token(app_name: &str, filename: &str, secret: Option<&s
     base64_url_encode(&result[..16])
 }
 
+/// Compute the HMAC signature binding a file token to an expiry and optional holder
+pub fn sign_download(file_token: &str, expires: i64, holder: Option<&str>, secret: &str) -> String {
+    base64_url_encode(
+        &download_mac(file_token, expires, holder, secret)
+            .finalize()
+            .into_bytes(),
+    )
+}
+
+/// Verify a signed download URL
+/// The signature is compared in constant time; expiry is checked against `now` (Unix seconds)
+pub fn verify_download(
+    file_token: &str,
+    expires: i64,
+    holder: Option<&str>,
+    signature: &str,
+    secret: &str,
+    now: i64,
+) -> Result<(), DownloadUrlError> {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature)
+        .map_err(|_| DownloadUrlError::InvalidSignature)?;
+
+    download_mac(file_token, expires, holder, secret)
+        .verify_slice(&signature)
+        .map_err(|_| DownloadUrlError::InvalidSignature)?;
+
+    // Only trust the expiry once we know it wasn't tampered with
+    if now > expires {
+        return Err(DownloadUrlError::Expired);
+    }
+
+    Ok(())
+}
+
+/// Keyed MAC over the signed fields of a download URL
+fn download_mac(file_token: &str, expires: i64, holder: Option<&str>, secret: &str) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+
+    mac.update(file_token.as_bytes());
+    mac.update(b"|");
+    mac.update(expires.to_string().as_bytes());
+    mac.update(b"|");
+    mac.update(holder.unwrap_or_default().as_bytes());
+
+    mac
+}
+
+/// Percent-encode a query parameter value (RFC 3986 unreserved characters are kept)
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
 /// Encode bytes as base64url (URL-safe base64 without padding)
 fn base64_url_encode(data: &[u8]) -> String {
     use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
@@ -86,4 +193,62 @@ mod tests {
             "Token length should be around 22 chars"
         );
     }
+
+    #[test]
+    fn test_signed_download_roundtrip() {
+        let signature = sign_download("file", 1_000, Some("alice"), "secret");
+        assert_eq!(
+            verify_download("file", 1_000, Some("alice"), &signature, "secret", 999),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_signed_download_expired() {
+        let signature = sign_download("file", 1_000, None, "secret");
+        assert_eq!(
+            verify_download("file", 1_000, None, &signature, "secret", 1_001),
+            Err(DownloadUrlError::Expired)
+        );
+    }
+
+    #[test]
+    fn test_signed_download_rejects_tampering() {
+        let signature = sign_download("file", 1_000, Some("alice"), "secret");
+
+        // Extended expiry
+        assert_eq!(
+            verify_download("file", 9_999, Some("alice"), &signature, "secret", 0),
+            Err(DownloadUrlError::InvalidSignature)
+        );
+        // Different holder
+        assert_eq!(
+            verify_download("file", 1_000, Some("bob"), &signature, "secret", 0),
+            Err(DownloadUrlError::InvalidSignature)
+        );
+        // Different file
+        assert_eq!(
+            verify_download("other", 1_000, Some("alice"), &signature, "secret", 0),
+            Err(DownloadUrlError::InvalidSignature)
+        );
+        // Different secret
+        assert_eq!(
+            verify_download("file", 1_000, Some("alice"), &signature, "other", 0),
+            Err(DownloadUrlError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_download_path_format() {
+        let signing = UrlSigning {
+            secret: "secret",
+            expires: 1_000,
+            holder: Some("alice smith"),
+        };
+        let path = signing.download_path("YourApp", "app_1.0.ipa");
+        let token = generate_download_token("YourApp", "app_1.0.ipa", Some("secret"));
+
+        assert!(path.starts_with(&format!("/download/{}?expires=1000&sig=", token)));
+        assert!(path.ends_with("&holder=alice%20smith"));
+    }
 }
@@ -0,0 +1,259 @@
+use crate::reload::{spawn_file_reloader, Shared};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use subtle::ConstantTimeEq;
+
+/// Thread-safe handle to the current token store
+pub type TokenStoreHandle = Shared<TokenStore>;
+
+/// Token store file structure (from TOKENS_FILE)
+#[derive(Debug, Default, Deserialize)]
+pub struct TokenStore {
+    #[serde(default)]
+    pub tokens: Vec<ApiToken>,
+}
+
+/// A named API token with an optional scope
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiToken {
+    /// Name of the token holder, used in logs and signed download URLs
+    pub name: String,
+    /// Secret value passed as the ?token= query parameter
+    pub token: String,
+    /// App names or bundle identifiers this token may access (all apps if absent)
+    #[serde(default)]
+    pub apps: Option<Vec<String>>,
+    /// Optional point in time after which the token is rejected
+    #[serde(default, rename = "expiresAt")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Disabled tokens are rejected without having to remove them
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+/// Reasons a presented token is rejected
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum TokenRejection {
+    #[error("unknown token")]
+    Unknown,
+    #[error("token {0} is disabled")]
+    Disabled(String),
+    #[error("token {0} has expired")]
+    Expired(String),
+}
+
+/// Identity and scope resolved from a valid token
+/// Inserted into request extensions by the auth middleware
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenGrant {
+    /// Name of the token holder (None for the legacy AUTH_TOKEN)
+    pub holder: Option<String>,
+    /// App names or bundle identifiers the holder may access (all apps if None)
+    pub apps: Option<Vec<String>>,
+}
+
+impl TokenGrant {
+    /// Grant with access to every app
+    pub fn unrestricted() -> Self {
+        Self {
+            holder: None,
+            apps: None,
+        }
+    }
+
+    /// Returns true if the grant covers an app known by any of the given names or bundle identifiers
+    pub fn allows<'a>(&self, identifiers: impl IntoIterator<Item = &'a str>) -> bool {
+        let Some(ref apps) = self.apps else {
+            return true;
+        };
+
+        identifiers
+            .into_iter()
+            .any(|identifier| apps.iter().any(|app| app == identifier))
+    }
+}
+
+impl ApiToken {
+    /// Checks that the token is usable at the given point in time
+    fn check(&self, now: DateTime<Utc>) -> Result<TokenGrant, TokenRejection> {
+        if self.disabled {
+            return Err(TokenRejection::Disabled(self.name.clone()));
+        }
+        if self.expires_at.is_some_and(|expires_at| now >= expires_at) {
+            return Err(TokenRejection::Expired(self.name.clone()));
+        }
+
+        Ok(TokenGrant {
+            holder: Some(self.name.clone()),
+            apps: self.apps.clone(),
+        })
+    }
+}
+
+impl TokenStore {
+    /// Resolves a presented token value
+    /// Every stored token is compared in constant time so lookups don't leak timing information
+    pub fn resolve(
+        &self,
+        presented: &str,
+        now: DateTime<Utc>,
+    ) -> Result<TokenGrant, TokenRejection> {
+        let mut matched = None;
+        for token in &self.tokens {
+            if bool::from(token.token.as_bytes().ct_eq(presented.as_bytes())) {
+                matched = Some(token);
+            }
+        }
+
+        matched.ok_or(TokenRejection::Unknown)?.check(now)
+    }
+
+    /// Resolves the holder named in a signed download URL
+    pub fn resolve_holder(
+        &self,
+        name: &str,
+        now: DateTime<Utc>,
+    ) -> Result<TokenGrant, TokenRejection> {
+        self.tokens
+            .iter()
+            .find(|token| token.name == name)
+            .ok_or(TokenRejection::Unknown)?
+            .check(now)
+    }
+}
+
+/// Reads, parses and validates the token store file
+pub fn load_token_store(path: &Path) -> Result<TokenStore> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read token store: {}", path.display()))?;
+    let store: TokenStore = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse token store: {}", path.display()))?;
+    validate_token_store(&store)?;
+    Ok(store)
+}
+
+/// Checks invariants that serde can't express
+fn validate_token_store(store: &TokenStore) -> Result<()> {
+    let mut names = HashSet::new();
+    let mut values = HashSet::new();
+
+    for token in &store.tokens {
+        if token.name.trim().is_empty() {
+            anyhow::bail!("Token with an empty name");
+        }
+        if token.token.is_empty() {
+            anyhow::bail!("Token {} has an empty value", token.name);
+        }
+        if !names.insert(token.name.as_str()) {
+            anyhow::bail!("Token name {} is used more than once", token.name);
+        }
+        if !values.insert(token.token.as_str()) {
+            anyhow::bail!("Token {} reuses the value of another token", token.name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Reloads the token store and swaps it in if it is valid
+/// On error the previous tokens are kept
+async fn reload(path: &Path, handle: &TokenStoreHandle) {
+    match load_token_store(path) {
+        Ok(store) => {
+            tracing::info!("Reloaded {} API tokens", store.tokens.len());
+            handle.replace(store).await;
+        }
+        Err(err) => {
+            tracing::error!(
+                "Rejected new token store, keeping the previous tokens: {:#}",
+                err
+            );
+        }
+    }
+}
+
+/// Starts reloading the token store when it changes on disk or when the process receives SIGHUP
+pub fn spawn_token_store_reloader(path: PathBuf, handle: TokenStoreHandle) -> Result<()> {
+    let store_path = path.clone();
+    spawn_file_reloader(path, move || {
+        let path = store_path.clone();
+        let handle = handle.clone();
+        async move { reload(&path, &handle).await }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> TokenStore {
+        serde_json::from_str(
+            r#"{
+                "tokens": [
+                    { "name": "alice", "token": "alice-secret" },
+                    { "name": "bob", "token": "bob-secret", "apps": ["YTLite", "com.example.app"] },
+                    { "name": "carol", "token": "carol-secret", "disabled": true },
+                    { "name": "dave", "token": "dave-secret", "expiresAt": "2025-01-01T00:00:00Z" }
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn now() -> DateTime<Utc> {
+        "2025-06-01T00:00:00Z".parse().unwrap()
+    }
+
+    #[test]
+    fn test_resolve_valid_tokens() {
+        let store = store();
+
+        let alice = store.resolve("alice-secret", now()).unwrap();
+        assert_eq!(alice.holder.as_deref(), Some("alice"));
+        assert!(alice.allows(["AnyApp"]));
+
+        let bob = store.resolve("bob-secret", now()).unwrap();
+        assert!(bob.allows(["YTLite"]));
+        assert!(bob.allows(["Renamed", "com.example.app"]));
+        assert!(!bob.allows(["OtherApp", "com.example.other"]));
+    }
+
+    #[test]
+    fn test_resolve_rejected_tokens() {
+        let store = store();
+
+        assert_eq!(store.resolve("nope", now()), Err(TokenRejection::Unknown));
+        assert_eq!(
+            store.resolve("carol-secret", now()),
+            Err(TokenRejection::Disabled("carol".to_string()))
+        );
+        assert_eq!(
+            store.resolve("dave-secret", now()),
+            Err(TokenRejection::Expired("dave".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_resolve_holder() {
+        let store = store();
+
+        assert!(store.resolve_holder("bob", now()).is_ok());
+        assert!(store.resolve_holder("carol", now()).is_err());
+        assert!(store.resolve_holder("nobody", now()).is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_duplicate_names() {
+        let store: TokenStore = serde_json::from_str(
+            r#"{ "tokens": [
+                { "name": "a", "token": "one" },
+                { "name": "a", "token": "two" }
+            ] }"#,
+        )
+        .unwrap();
+        assert!(validate_token_store(&store).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use std::cmp::Ordering;
+
+/// Compares version strings as dotted numbers with an optional pre-release suffix
+/// e.g. `5.2b1 < 5.2 < 5.2.1 < 5.10`; missing components count as zero, so `1.0 == 1.0.0`.
+/// A suffix (anything after the dotted numbers, like `b1`, `-beta.2` or `-1`) marks a
+/// pre-release, which sorts before the plain version; suffixes compare naturally.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (a_numbers, a_suffix) = split_suffix(a);
+    let (b_numbers, b_suffix) = split_suffix(b);
+
+    let a_parts: Vec<&str> = a_numbers.split('.').collect();
+    let b_parts: Vec<&str> = b_numbers.split('.').collect();
+    let numbers = (0..a_parts.len().max(b_parts.len()))
+        .map(|i| {
+            compare_numbers(
+                a_parts.get(i).copied().unwrap_or("0"),
+                b_parts.get(i).copied().unwrap_or("0"),
+            )
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal);
+
+    numbers.then_with(|| match (a_suffix.is_empty(), b_suffix.is_empty()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => compare_natural(a_suffix, b_suffix),
+    })
+}
+
+/// Splits "5.2b1" into ("5.2", "b1")
+fn split_suffix(version: &str) -> (&str, &str) {
+    let version = version.trim();
+    let end = version
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(version.len());
+    let (numbers, suffix) = version.split_at(end);
+
+    (
+        numbers.trim_end_matches('.'),
+        suffix.trim_start_matches(['-', '.', '+']),
+    )
+}
+
+/// Compares digit strings by value without overflowing on long build numbers
+fn compare_numbers(a: &str, b: &str) -> Ordering {
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+/// Compares strings run by run, digit runs by value: "b2" < "b10", "alpha" < "beta"
+fn compare_natural(a: &str, b: &str) -> Ordering {
+    let mut a_runs = runs(a);
+    let mut b_runs = runs(b);
+
+    loop {
+        match (a_runs.next(), b_runs.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_run), Some(b_run)) => {
+                let a_numeric = a_run.starts_with(|c: char| c.is_ascii_digit());
+                let b_numeric = b_run.starts_with(|c: char| c.is_ascii_digit());
+                let ordering = match (a_numeric, b_numeric) {
+                    (true, true) => compare_numbers(a_run, b_run),
+                    _ => a_run.to_lowercase().cmp(&b_run.to_lowercase()),
+                };
+                if ordering.is_ne() {
+                    return ordering;
+                }
+            }
+        }
+    }
+}
+
+/// Splits a string into alternating digit and non-digit runs, dropping separators
+fn runs(s: &str) -> impl Iterator<Item = &str> {
+    let mut rest = s;
+    std::iter::from_fn(move || {
+        rest = rest.trim_start_matches(['-', '.', '+', '_']);
+        let first = rest.chars().next()?;
+        let numeric = first.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != numeric || matches!(c, '-' | '.' | '+' | '_'))
+            .unwrap_or(rest.len());
+        let (run, tail) = rest.split_at(end);
+        rest = tail;
+        Some(run)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("1.2", "1.10"), Ordering::Less);
+        assert_eq!(compare_versions("20.26.7", "20.26"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("2", "1.99"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_compare_versions_pre_release() {
+        assert_eq!(compare_versions("5.2b1", "5.2"), Ordering::Less);
+        assert_eq!(compare_versions("5.2b1", "5.1"), Ordering::Greater);
+        assert_eq!(compare_versions("5.2b2", "5.2b10"), Ordering::Less);
+        assert_eq!(compare_versions("1.0-alpha", "1.0-beta.2"), Ordering::Less);
+        assert_eq!(compare_versions("1.0-rc1", "1.0"), Ordering::Less);
+    }
+}